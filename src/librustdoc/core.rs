@@ -13,6 +13,7 @@
 use rustc_lint::{late_lint_mod, MissingDoc};
 use rustc_middle::hir::nested_filter;
 use rustc_middle::ty::{ParamEnv, Ty, TyCtxt};
+use rustc_middle::util::Providers;
 use rustc_session::config::{self, CrateType, ErrorOutputType, ResolveDocLinks};
 use rustc_session::lint;
 use rustc_session::Session;
@@ -264,32 +265,7 @@ pub(crate) fn create_config(
         parse_sess_created: None,
         hash_untracked_state: None,
         register_lints: Some(Box::new(crate::lint::register_lints)),
-        override_queries: Some(|_sess, providers| {
-            // We do not register late module lints, so this only runs `MissingDoc`.
-            // Most lints will require typechecking, so just don't run them.
-            providers.lint_mod = |tcx, module_def_id| late_lint_mod(tcx, module_def_id, MissingDoc);
-            // hack so that `used_trait_imports` won't try to call typeck
-            providers.used_trait_imports = |_, _| {
-                static EMPTY_SET: LazyLock<UnordSet<LocalDefId>> = LazyLock::new(UnordSet::default);
-                &EMPTY_SET
-            };
-            // In case typeck does end up being called, don't ICE in case there were name resolution errors
-            providers.typeck = move |tcx, def_id| {
-                // Closures' tables come from their outermost function,
-                // as they are part of the same "inference environment".
-                // This avoids emitting errors for the parent twice (see similar code in `typeck_with_fallback`)
-                let typeck_root_def_id = tcx.typeck_root_def_id(def_id.to_def_id()).expect_local();
-                if typeck_root_def_id != def_id {
-                    return tcx.typeck(typeck_root_def_id);
-                }
-
-                let hir = tcx.hir();
-                let body = hir.body(hir.body_owned_by(def_id));
-                debug!("visiting body for {def_id:?}");
-                EmitIgnoredResolutionErrors::new(tcx).visit_body(body);
-                (rustc_interface::DEFAULT_QUERY_PROVIDERS.typeck)(tcx, def_id)
-            };
-        }),
+        override_queries: Some(override_queries),
         make_codegen_backend: None,
         registry: rustc_driver::diagnostics_registry(),
         ice_file: None,
@@ -298,12 +274,37 @@ pub(crate) fn create_config(
     }
 }
 
-pub(crate) fn run_global_ctxt(
-    tcx: TyCtxt<'_>,
-    show_coverage: bool,
-    render_options: RenderOptions,
-    output_format: OutputFormat,
-) -> (clean::Crate, RenderOptions, Cache) {
+/// Replaces the queries rustdoc does not want to run as they would for a normal compilation.
+pub(crate) fn override_queries(_sess: &Session, providers: &mut Providers) {
+    // We do not register late module lints, so this only runs `MissingDoc`.
+    // Most lints will require typechecking, so just don't run them.
+    providers.lint_mod = |tcx, module_def_id| late_lint_mod(tcx, module_def_id, MissingDoc);
+    // hack so that `used_trait_imports` won't try to call typeck
+    providers.used_trait_imports = |_, _| {
+        static EMPTY_SET: LazyLock<UnordSet<LocalDefId>> = LazyLock::new(UnordSet::default);
+        &EMPTY_SET
+    };
+    // In case typeck does end up being called, don't ICE in case there were name resolution errors
+    providers.typeck = move |tcx, def_id| {
+        // Closures' tables come from their outermost function,
+        // as they are part of the same "inference environment".
+        // This avoids emitting errors for the parent twice (see similar code in `typeck_with_fallback`)
+        let typeck_root_def_id = tcx.typeck_root_def_id(def_id.to_def_id()).expect_local();
+        if typeck_root_def_id != def_id {
+            return tcx.typeck(typeck_root_def_id);
+        }
+
+        let hir = tcx.hir();
+        let body = hir.body(hir.body_owned_by(def_id));
+        debug!("visiting body for {def_id:?}");
+        EmitIgnoredResolutionErrors::new(tcx).visit_body(body);
+        (rustc_interface::DEFAULT_QUERY_PROVIDERS.typeck)(tcx, def_id)
+    };
+}
+
+/// Type-checks everything other than function bodies in the local crate, aborting if any
+/// errors were found.
+pub(crate) fn check_item_types(tcx: TyCtxt<'_>) {
     // Certain queries assume that some checks were run elsewhere
     // (see https://github.com/rust-lang/rust/pull/73566#issuecomment-656954425),
     // so type-check everything other than function bodies in this crate before running lints.
@@ -329,6 +330,15 @@ pub(crate) fn run_global_ctxt(
     });
 
     tcx.dcx().abort_if_errors();
+}
+
+pub(crate) fn run_global_ctxt(
+    tcx: TyCtxt<'_>,
+    show_coverage: bool,
+    render_options: RenderOptions,
+    output_format: OutputFormat,
+) -> (clean::Crate, RenderOptions, Cache) {
+    check_item_types(tcx);
     tcx.sess.time("missing_docs", || rustc_lint::check_crate(tcx));
     tcx.sess.time("check_mod_attrs", || {
         tcx.hir().for_each_module(|module| tcx.ensure().check_mod_attrs(module))
@@ -37,12 +37,14 @@
 extern crate rustc_ast;
 extern crate rustc_ast_pretty;
 extern crate rustc_attr;
+extern crate rustc_borrowck;
 extern crate rustc_const_eval;
 extern crate rustc_data_structures;
 extern crate rustc_driver;
 extern crate rustc_errors;
 extern crate rustc_expand;
 extern crate rustc_feature;
+extern crate rustc_graphviz;
 extern crate rustc_hir;
 extern crate rustc_hir_analysis;
 extern crate rustc_hir_pretty;
@@ -56,6 +58,7 @@
 extern crate rustc_macros;
 extern crate rustc_metadata;
 extern crate rustc_middle;
+extern crate rustc_mir_dataflow;
 extern crate rustc_parse;
 extern crate rustc_passes;
 extern crate rustc_resolve;
@@ -685,9 +688,77 @@ fn opts() -> Vec<RustcOptGroup> {
     ]
 }
 
+/// The options of the `tooling` binary, on top of the ones of rustdoc. They are kept out of
+/// `opts()` so that `rustdoc --help` does not list them.
+fn tooling_opts() -> Vec<RustcOptGroup> {
+    let stable: fn(_, fn(&mut getopts::Options) -> &mut _) -> _ = RustcOptGroup::stable;
+    vec![
+        stable("tooling-out-dir", |o| {
+            o.optopt(
+                "",
+                "tooling-out-dir",
+                "which directory to place the tooling results in",
+                "PATH",
+            )
+        }),
+        stable("tooling-format", |o| {
+            o.optopt("", "tooling-format", "the format of the tooling results", "json|jsonl|csv")
+        }),
+        stable("tooling-analysis", |o| {
+            o.optmulti(
+                "",
+                "tooling-analysis",
+                "Comma separated list of tooling analyses to run",
+                "[vars,exprs,calls,callgraph,mono-callgraph,closures,borrows,liveness,drops,unsafe,panics,coroutines,mono-locals]",
+            )
+        }),
+        stable("tooling-analysis-only", |o| {
+            o.optflagmulti(
+                "",
+                "tooling-analysis-only",
+                "only run the tooling analyses, without generating documentation",
+            )
+        }),
+        stable("tooling-path-style", |o| {
+            o.optopt(
+                "",
+                "tooling-path-style",
+                "how types and paths are printed in the tooling results",
+                "trimmed|crate-relative|full|canonical",
+            )
+        }),
+        stable("tooling-reveal-opaque-types", |o| {
+            o.optflagmulti(
+                "",
+                "tooling-reveal-opaque-types",
+                "also emit the hidden types of `impl Trait` types and futures, with their size",
+            )
+        }),
+        stable("tooling-trait", |o| {
+            o.optmulti(
+                "",
+                "tooling-trait",
+                "Comma separated list of paths of further traits to check variable types against",
+                "PATHS",
+            )
+        }),
+        stable("tooling-skip-expansions", |o| {
+            o.optflagmulti(
+                "",
+                "tooling-skip-expansions",
+                "leave out tooling results located in code produced by macro expansions",
+            )
+        }),
+    ]
+}
+
 fn usage(argv0: &str) {
+    print_usage(argv0, opts())
+}
+
+fn print_usage(argv0: &str, option_groups: Vec<RustcOptGroup>) {
     let mut options = getopts::Options::new();
-    for option in opts() {
+    for option in option_groups {
         (option.apply)(&mut options);
     }
     println!("{}", options.usage(&format!("{argv0} [options] <input>")));
@@ -747,7 +818,7 @@ fn tooling_main_args(
     let args = rustc_driver::args::arg_expand_all(early_dcx, at_args);
 
     let mut options = getopts::Options::new();
-    for option in opts() {
+    for option in opts().into_iter().chain(tooling_opts()) {
         (option.apply)(&mut options);
     }
     let matches = match options.parse(&args) {
@@ -756,6 +827,10 @@ fn tooling_main_args(
             early_dcx.early_fatal(err.to_string());
         }
     };
+    if args.is_empty() || matches.opt_present("h") || matches.opt_present("help") {
+        print_usage("tooling", opts().into_iter().chain(tooling_opts()).collect());
+        return Ok(());
+    }
 
     // Note that we discard any distinction between different non-zero exit
     // codes from `from_matches` here.
@@ -773,6 +848,7 @@ fn tooling_main_args(
 
     let diag =
         core::new_dcx(options.error_format, None, options.diagnostic_width, &options.unstable_opts);
+    let tooling_options = tooling::ToolingOptions::new(&matches, &diag)?;
 
     match (options.should_test, options.markdown_input()) {
         (true, true) => return wrap_return(&diag, markdown::test(options)),
@@ -813,7 +889,10 @@ fn tooling_main_args(
     let scrape_examples_options = options.scrape_examples_options.clone();
     let bin_crate = options.bin_crate;
 
-    let config = core::create_config(options, &render_options, using_internal_features);
+    let mut config = core::create_config(options, &render_options, using_internal_features);
+    if tooling_options.analyses.iter().any(|analysis| analysis.needs_borrowck_bodies()) {
+        config.override_queries = Some(tooling::override_queries);
+    }
 
     interface::run_compiler(config, |compiler| {
         let sess = &compiler.sess;
@@ -830,6 +909,17 @@ fn tooling_main_args(
             }
 
             gcx.enter(|tcx| {
+                // Borrowck can run before the tooling analyses, e.g. for `const fn`s evaluated
+                // while type checking, so start keeping its bodies aside right away.
+                let _borrowck_bodies = tooling::BorrowckBodies::new(tcx);
+                if tooling_options.analysis_only {
+                    // The tooling passes only need the type-checked HIR, so skip cleaning the
+                    // crate and rendering its documentation.
+                    sess.time("check_item_types", || core::check_item_types(tcx));
+                    info!("finished with rustc");
+                    return sess.time("tooling", || get_infos(tcx, &tooling_options));
+                }
+
                 let (krate, render_opts, mut cache) = sess.time("run_global_ctxt", || {
                     core::run_global_ctxt(tcx, show_coverage, render_options, output_format)
                 });
@@ -857,7 +947,7 @@ fn tooling_main_args(
                     return Ok(());
                 }
 
-                get_infos(tcx);
+                sess.time("tooling", || get_infos(tcx, &tooling_options))?;
 
                 info!("going to format");
                 match output_format {
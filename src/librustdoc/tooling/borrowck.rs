@@ -0,0 +1,132 @@
+//! Access to the MIR bodies borrowck works on.
+//!
+//! Borrowck runs on the output of `mir_promoted`, which later MIR passes steal, so the bodies
+//! are kept aside, together with their borrowck facts, when `mir_borrowck` runs while a
+//! [`BorrowckBodies`] guard is alive.
+
+use std::cell::RefCell;
+
+use rustc_borrowck::consumers::{self, BodyWithBorrowckFacts, ConsumerOptions};
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::mir::BorrowCheckResult;
+use rustc_middle::ty::TyCtxt;
+use rustc_middle::util::Providers;
+use rustc_session::Session;
+
+thread_local! {
+    /// The bodies kept aside, or `None` when no `BorrowckBodies` guard is alive.
+    static BODIES: RefCell<Option<FxHashMap<LocalDefId, BodyWithBorrowckFacts<'static>>>> =
+        const { RefCell::new(None) };
+}
+
+/// Keeps the bodies borrowck works on aside while it is alive, and drops them with it.
+///
+/// The bodies are allocated in the arenas of the `TyCtxt`, so the guard borrows it to make
+/// sure they are gone before the arenas are.
+pub(crate) struct BorrowckBodies<'tcx> {
+    _tcx: TyCtxt<'tcx>,
+}
+
+impl<'tcx> BorrowckBodies<'tcx> {
+    pub(crate) fn new(tcx: TyCtxt<'tcx>) -> Self {
+        BODIES.with(|bodies| {
+            let mut bodies = bodies.borrow_mut();
+            assert!(bodies.is_none(), "borrowck bodies are already being kept aside");
+            *bodies = Some(FxHashMap::default());
+        });
+        BorrowckBodies { _tcx: tcx }
+    }
+}
+
+impl Drop for BorrowckBodies<'_> {
+    fn drop(&mut self) {
+        BODIES.with(|bodies| bodies.borrow_mut().take());
+    }
+}
+
+/// Overrides the queries like rustdoc does, and additionally keeps aside the bodies
+/// borrowck works on.
+pub(crate) fn override_queries(sess: &Session, providers: &mut Providers) {
+    crate::core::override_queries(sess, providers);
+    providers.mir_borrowck = mir_borrowck;
+}
+
+/// Borrow-checks `def_id` with the default provider, after keeping its body aside if a
+/// `BorrowckBodies` guard is alive.
+///
+/// `get_body_with_borrowck_facts` does not hand out the `BorrowCheckResult` of its run, so the
+/// kept-aside bodies are borrow-checked twice. `override_queries` is only installed for the
+/// analyses that need these bodies, see `ToolingAnalysis::needs_borrowck_bodies`, so the other
+/// analyses do not pay for it.
+fn mir_borrowck(tcx: TyCtxt<'_>, def_id: LocalDefId) -> &BorrowCheckResult<'_> {
+    let keep_aside = BODIES.with(|bodies| bodies.borrow().is_some());
+    if keep_aside && tcx.typeck(def_id).tainted_by_errors.is_none() {
+        let body_with_facts = consumers::get_body_with_borrowck_facts(
+            tcx,
+            def_id,
+            ConsumerOptions::RegionInferenceContext,
+        );
+        // SAFETY: The body is only handed out again by `with_borrowck_body`, which restores
+        // the lifetime of the `TyCtxt` it was created with, and is dropped with the
+        // `BorrowckBodies` guard, which cannot outlive that `TyCtxt`.
+        let body_with_facts: BodyWithBorrowckFacts<'static> =
+            unsafe { std::mem::transmute(body_with_facts) };
+        BODIES.with(|bodies| {
+            if let Some(bodies) = bodies.borrow_mut().as_mut() {
+                bodies.insert(def_id, body_with_facts);
+            }
+        });
+    }
+    (rustc_interface::DEFAULT_QUERY_PROVIDERS.mir_borrowck)(tcx, def_id)
+}
+
+/// Calls `f` with every function and closure of the local crate and the body borrowck checked
+/// for it. Bodies with type errors are skipped, and a warning is emitted for any other body
+/// that was not kept aside.
+pub(crate) fn for_each_borrowck_body<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    mut f: impl FnMut(LocalDefId, &BodyWithBorrowckFacts<'tcx>),
+) {
+    for def_id in tcx.hir().body_owners() {
+        if !matches!(tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn | DefKind::Closure) {
+            continue;
+        }
+        let found = with_borrowck_body(tcx, def_id, |body_with_facts| f(def_id, body_with_facts));
+        if found.is_none() && tcx.typeck(def_id).tainted_by_errors.is_none() {
+            tcx.dcx().span_warn(
+                tcx.def_span(def_id),
+                "the tooling analyses skip this body, as it was borrow-checked before its MIR \
+                could be kept aside",
+            );
+        }
+    }
+}
+
+/// Calls `f` with the body borrowck checked for `def_id` and its borrowck facts.
+///
+/// Returns `None` if the body was not kept aside, which happens when it has type errors, when
+/// `override_queries` is not in use, or when it was borrow-checked while no `BorrowckBodies`
+/// guard was alive.
+fn with_borrowck_body<'tcx, R>(
+    tcx: TyCtxt<'tcx>,
+    def_id: LocalDefId,
+    f: impl FnOnce(&BodyWithBorrowckFacts<'tcx>) -> R,
+) -> Option<R> {
+    tcx.ensure().mir_borrowck(def_id);
+    // Take the body out while `f` runs, in case it ends up borrow-checking other bodies.
+    let body_with_facts = BODIES.with(|bodies| bodies.borrow_mut().as_mut()?.remove(&def_id))?;
+    // SAFETY: The body was created with `tcx` in `mir_borrowck`.
+    let result = f(unsafe {
+        std::mem::transmute::<&BodyWithBorrowckFacts<'static>, &BodyWithBorrowckFacts<'tcx>>(
+            &body_with_facts,
+        )
+    });
+    BODIES.with(|bodies| {
+        if let Some(bodies) = bodies.borrow_mut().as_mut() {
+            bodies.insert(def_id, body_with_facts);
+        }
+    });
+    Some(result)
+}
@@ -0,0 +1,149 @@
+//! Moves, copies, borrows and drops of user variables, read from the MIR borrowck works on.
+
+use rustc_borrowck::borrow_set::BorrowSet;
+use rustc_middle::mir::visit::Visitor;
+use rustc_middle::mir::{
+    self, Body, Location, Mutability, Operand, Place, ProjectionElem, Rvalue, TerminatorKind,
+};
+use rustc_middle::ty::TyCtxt;
+use serde::{Deserialize, Serialize};
+
+use super::borrowck::for_each_borrowck_body;
+use super::places::{place_to_string, UserVar, UserVars};
+use super::{paths, Record, SpanInfo};
+
+/// What happens to a variable, or a place rooted at it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Move,
+    Copy,
+    SharedBorrow,
+    MutBorrow,
+    /// A shared borrow through a reference the variable holds, e.g. `&*r`.
+    SharedReborrow,
+    /// A mutable borrow through a reference the variable holds, e.g. `&mut *r`.
+    MutReborrow,
+    /// A `&raw const` pointer, e.g. from `ptr::addr_of!`.
+    ConstRawBorrow,
+    /// A `&raw mut` pointer, e.g. from `ptr::addr_of_mut!`.
+    MutRawBorrow,
+    Drop,
+}
+
+/// An event on a user variable.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EventInfo {
+    #[serde(flatten)]
+    span: SpanInfo,
+    /// The function or closure the event happens in.
+    function: String,
+    variable: String,
+    /// Where `variable` is declared.
+    declared_at: SpanInfo,
+    /// The place the event applies to, e.g. `x` or `(*x).field`.
+    place: String,
+    ty: String,
+    event: EventKind,
+    /// The index borrowck gives the borrow, for the borrows it tracks.
+    borrow_index: Option<usize>,
+}
+
+struct EventCollector<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    body: &'a Body<'tcx>,
+    borrow_set: &'a BorrowSet<'tcx>,
+    function: String,
+    vars: UserVars<'tcx>,
+    events: Vec<EventInfo>,
+}
+
+impl<'a, 'tcx> EventCollector<'a, 'tcx> {
+    /// Whether borrowing `place` goes through a reference held by `var`.
+    fn is_reborrow(&self, var: &UserVar<'tcx>, place: Place<'tcx>) -> bool {
+        place.iter_projections().skip(var.place.projection.len()).any(|(base, elem)| {
+            matches!(elem, ProjectionElem::Deref) && base.ty(self.body, self.tcx).ty.is_ref()
+        })
+    }
+
+    fn record(&mut self, place: Place<'tcx>, location: Location, event: EventKind) {
+        let Some(var) = self.vars.root_of(place) else { return };
+        let event = match event {
+            EventKind::SharedBorrow if self.is_reborrow(var, place) => EventKind::SharedReborrow,
+            EventKind::MutBorrow if self.is_reborrow(var, place) => EventKind::MutReborrow,
+            event => event,
+        };
+        let borrow_index = match event {
+            EventKind::Move | EventKind::Copy | EventKind::Drop => None,
+            _ => self.borrow_set.location_map.get_index_of(&location),
+        };
+        let event_info = EventInfo {
+            span: SpanInfo::new(self.tcx, self.body.source_info(location).span),
+            function: self.function.clone(),
+            variable: var.name.to_string(),
+            declared_at: SpanInfo::new(self.tcx, var.span),
+            place: place_to_string(self.tcx, self.body, var, place),
+            ty: paths::print(self.tcx, place.ty(self.body, self.tcx).ty),
+            event,
+            borrow_index,
+        };
+        self.events.push(event_info);
+    }
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for EventCollector<'a, 'tcx> {
+    fn visit_operand(&mut self, operand: &Operand<'tcx>, location: Location) {
+        match *operand {
+            Operand::Move(place) => self.record(place, location, EventKind::Move),
+            Operand::Copy(place) => self.record(place, location, EventKind::Copy),
+            Operand::Constant(_) => {}
+        }
+    }
+
+    fn visit_rvalue(&mut self, rvalue: &Rvalue<'tcx>, location: Location) {
+        match *rvalue {
+            Rvalue::Ref(_, kind, place) => {
+                let event = match kind {
+                    mir::BorrowKind::Shared => EventKind::SharedBorrow,
+                    mir::BorrowKind::Mut { .. } => EventKind::MutBorrow,
+                    // The fake borrows of match guards are not written by the user.
+                    mir::BorrowKind::Fake => return,
+                };
+                self.record(place, location, event);
+            }
+            Rvalue::AddressOf(mutability, place) => {
+                let event = match mutability {
+                    Mutability::Not => EventKind::ConstRawBorrow,
+                    Mutability::Mut => EventKind::MutRawBorrow,
+                };
+                self.record(place, location, event);
+            }
+            _ => self.super_rvalue(rvalue, location),
+        }
+    }
+
+    fn visit_terminator(&mut self, terminator: &mir::Terminator<'tcx>, location: Location) {
+        if let TerminatorKind::Drop { place, .. } = terminator.kind {
+            self.record(place, location, EventKind::Drop);
+        }
+        self.super_terminator(terminator, location);
+    }
+}
+
+pub(crate) fn collect(tcx: TyCtxt<'_>) -> Vec<Record> {
+    let mut records = Vec::new();
+    for_each_borrowck_body(tcx, |def_id, body_with_facts| {
+        let body = &body_with_facts.body;
+        let mut collector = EventCollector {
+            tcx,
+            body,
+            borrow_set: &body_with_facts.borrow_set,
+            function: paths::def_path(tcx, def_id),
+            vars: UserVars::new(body),
+            events: Vec::new(),
+        };
+        collector.visit_body(body);
+        records.extend(collector.events.into_iter().map(Record::Event));
+    });
+    records
+}
@@ -0,0 +1,370 @@
+//! A call graph of the local crate, built from the `Call` terminators of optimized MIR.
+//!
+//! Only the bodies of local functions are walked: functions of other crates and
+//! compiler-generated shims show up as leaves.
+
+use rustc_data_structures::fx::{FxHashSet, FxIndexMap};
+use rustc_graphviz as dot;
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::{self, TerminatorKind};
+use rustc_middle::ty::{self, Instance, InstanceDef, Ty, TyCtxt};
+use serde::{Deserialize, Serialize};
+
+use super::{paths, Record, SpanInfo};
+
+/// Which functions the nodes of a call graph stand for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum CallGraphView {
+    /// One node per function. Calls in generic functions are resolved as far as possible
+    /// without knowing the generic arguments.
+    Generic,
+    /// One node per monomorphized instance reachable from the non-generic functions and
+    /// closures of the crate.
+    Instance,
+}
+
+/// What a node of the call graph stands for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    /// A function of the local crate, whose calls are part of the graph.
+    Local,
+    /// A function of another crate.
+    Extern,
+    /// A compiler-generated shim, such as `FnOnce::call_once` of a closure.
+    Shim,
+    /// The unknown functions called through function pointers of one type.
+    FnPointer,
+}
+
+/// How a call reaches its callee.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// A direct call of a function or method.
+    Static,
+    /// A call through the vtable of a `dyn Trait`.
+    Dynamic,
+    /// A call through a function pointer.
+    FnPointer,
+    /// A call of a closure through one of the `Fn` traits.
+    Closure,
+}
+
+impl EdgeKind {
+    fn name(self) -> &'static str {
+        match self {
+            EdgeKind::Static => "static",
+            EdgeKind::Dynamic => "dynamic",
+            EdgeKind::FnPointer => "fn pointer",
+            EdgeKind::Closure => "closure",
+        }
+    }
+}
+
+/// A function of the call graph.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CallNodeInfo {
+    id: usize,
+    name: String,
+    kind: NodeKind,
+    /// The crate defining the function, if the node stands for a single function.
+    crate_name: Option<String>,
+    /// Where a local function is defined.
+    #[serde(flatten)]
+    span: Option<SpanInfo>,
+}
+
+/// A call from `caller` to `callee`, both ids of nodes.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CallEdgeInfo {
+    caller: usize,
+    callee: usize,
+    kind: EdgeKind,
+    #[serde(flatten)]
+    span: SpanInfo,
+}
+
+pub(crate) struct CallGraph {
+    nodes: Vec<CallNodeInfo>,
+    edges: Vec<CallEdgeInfo>,
+}
+
+impl CallGraph {
+    /// Renders the graph in Graphviz DOT syntax.
+    pub(crate) fn to_dot(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        dot::render(self, &mut out).expect("writing to a `Vec` cannot fail");
+        out
+    }
+
+    pub(crate) fn into_records(self) -> Vec<Record> {
+        let nodes = self.nodes.into_iter().map(Record::CallNode);
+        let edges = self.edges.into_iter().map(Record::CallEdge);
+        nodes.chain(edges).collect()
+    }
+}
+
+impl<'a> dot::Labeller<'a> for CallGraph {
+    type Node = usize;
+    type Edge = usize;
+
+    fn graph_id(&'a self) -> dot::Id<'a> {
+        dot::Id::new("callgraph").unwrap()
+    }
+
+    fn node_id(&'a self, n: &usize) -> dot::Id<'a> {
+        dot::Id::new(format!("n{n}")).unwrap()
+    }
+
+    fn node_label(&'a self, n: &usize) -> dot::LabelText<'a> {
+        dot::LabelText::label(self.nodes[*n].name.as_str())
+    }
+
+    fn node_shape(&'a self, n: &usize) -> Option<dot::LabelText<'a>> {
+        let shape = match self.nodes[*n].kind {
+            NodeKind::Local => "box",
+            NodeKind::Extern => "ellipse",
+            NodeKind::Shim => "hexagon",
+            NodeKind::FnPointer => "diamond",
+        };
+        Some(dot::LabelText::label(shape))
+    }
+
+    fn edge_label(&'a self, e: &usize) -> dot::LabelText<'a> {
+        dot::LabelText::label(self.edges[*e].kind.name())
+    }
+
+    fn edge_style(&'a self, e: &usize) -> dot::Style {
+        match self.edges[*e].kind {
+            EdgeKind::Static => dot::Style::Solid,
+            EdgeKind::Dynamic => dot::Style::Dashed,
+            EdgeKind::FnPointer => dot::Style::Dotted,
+            EdgeKind::Closure => dot::Style::Bold,
+        }
+    }
+}
+
+impl<'a> dot::GraphWalk<'a> for CallGraph {
+    type Node = usize;
+    type Edge = usize;
+
+    fn nodes(&'a self) -> dot::Nodes<'a, usize> {
+        (0..self.nodes.len()).collect()
+    }
+
+    fn edges(&'a self) -> dot::Edges<'a, usize> {
+        (0..self.edges.len()).collect()
+    }
+
+    fn source(&'a self, e: &usize) -> usize {
+        self.edges[*e].caller
+    }
+
+    fn target(&'a self, e: &usize) -> usize {
+        self.edges[*e].callee
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum NodeKey<'tcx> {
+    Item(DefId),
+    Instance(Instance<'tcx>),
+    FnPointer(Ty<'tcx>),
+}
+
+struct CallGraphBuilder<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    view: CallGraphView,
+    nodes: FxIndexMap<NodeKey<'tcx>, CallNodeInfo>,
+    edges: Vec<CallEdgeInfo>,
+}
+
+impl<'tcx> CallGraphBuilder<'tcx> {
+    /// Returns the id of the node for `key`, adding the node if it is new.
+    fn node(&mut self, key: NodeKey<'tcx>) -> usize {
+        if let Some(id) = self.nodes.get_index_of(&key) {
+            return id;
+        }
+        let tcx = self.tcx;
+        let (name, def_id) = match key {
+            NodeKey::Item(def_id) => (paths::def_path(tcx, def_id), Some(def_id)),
+            NodeKey::Instance(instance) => match instance.def {
+                InstanceDef::Item(def_id) | InstanceDef::Intrinsic(def_id) => {
+                    (paths::instance_path(tcx, instance), Some(def_id))
+                }
+                _ => (paths::instance_path(tcx, instance), None),
+            },
+            NodeKey::FnPointer(ty) => (paths::print(tcx, ty), None),
+        };
+        let kind = match (key, def_id) {
+            (NodeKey::FnPointer(_), _) => NodeKind::FnPointer,
+            (_, None) => NodeKind::Shim,
+            (_, Some(def_id)) if def_id.is_local() && !tcx.is_foreign_item(def_id) => {
+                NodeKind::Local
+            }
+            (_, Some(_)) => NodeKind::Extern,
+        };
+        let id = self.nodes.len();
+        self.nodes.insert(
+            key,
+            CallNodeInfo {
+                id,
+                name,
+                kind,
+                crate_name: def_id.map(|def_id| tcx.crate_name(def_id.krate).to_string()),
+                span: def_id
+                    .and_then(|def_id| def_id.as_local())
+                    .map(|def_id| SpanInfo::new(tcx, tcx.def_span(def_id))),
+            },
+        );
+        id
+    }
+
+    /// Resolves the callee of a call of a function of type `func_ty`, which must not mention
+    /// generic parameters that are instantiated by the view.
+    fn callee(
+        &self,
+        func_ty: Ty<'tcx>,
+        param_env: ty::ParamEnv<'tcx>,
+    ) -> Option<(NodeKey<'tcx>, EdgeKind)> {
+        let tcx = self.tcx;
+        let (def_id, args) = match *func_ty.kind() {
+            ty::FnPtr(_) => return Some((NodeKey::FnPointer(func_ty), EdgeKind::FnPointer)),
+            ty::FnDef(def_id, args) => (def_id, args),
+            _ => return None,
+        };
+
+        // Closures are called through the methods of the `Fn` traits, with the closure as the
+        // `self` type.
+        let closure = tcx
+            .trait_of_item(def_id)
+            .filter(|&trait_def_id| tcx.fn_trait_kind_from_def_id(trait_def_id).is_some())
+            .and_then(|_| match *args.type_at(0).kind() {
+                ty::Closure(closure_def_id, _) => Some(closure_def_id),
+                _ => None,
+            });
+        let instance = Instance::resolve(tcx, param_env, def_id, args).ok().flatten();
+        let kind = match (instance, closure) {
+            (Some(Instance { def: InstanceDef::Virtual(..), .. }), _) => EdgeKind::Dynamic,
+            (_, Some(_)) => EdgeKind::Closure,
+            _ => EdgeKind::Static,
+        };
+
+        let key = match self.view {
+            CallGraphView::Generic => match (instance, closure) {
+                (_, Some(closure_def_id)) => NodeKey::Item(closure_def_id),
+                // Trait methods resolved to an impl stand for the method of the impl.
+                (Some(Instance { def: InstanceDef::Item(resolved), .. }), None) => {
+                    NodeKey::Item(resolved)
+                }
+                _ => NodeKey::Item(def_id),
+            },
+            CallGraphView::Instance => match instance {
+                Some(instance) if kind != EdgeKind::Dynamic => NodeKey::Instance(instance),
+                // For virtual calls, stand for the trait method of the `dyn Trait`.
+                _ => NodeKey::Instance(Instance::new(def_id, tcx.erase_regions(args))),
+            },
+        };
+        Some((key, kind))
+    }
+
+    /// Adds an edge for every call in `body`, instantiating the type of the called function
+    /// with `instantiate`, and returns the callees.
+    fn add_calls(
+        &mut self,
+        caller: usize,
+        body: &mir::Body<'tcx>,
+        param_env: ty::ParamEnv<'tcx>,
+        instantiate: impl Fn(Ty<'tcx>) -> Ty<'tcx>,
+    ) -> Vec<NodeKey<'tcx>> {
+        let mut callees = Vec::new();
+        for block in body.basic_blocks.iter() {
+            let terminator = block.terminator();
+            let TerminatorKind::Call { func, .. } = &terminator.kind else { continue };
+            let func_ty = instantiate(func.ty(body, self.tcx));
+            let Some((key, kind)) = self.callee(func_ty, param_env) else { continue };
+            let callee = self.node(key);
+            let span = SpanInfo::new(self.tcx, terminator.source_info.span);
+            self.edges.push(CallEdgeInfo { caller, callee, kind, span });
+            callees.push(key);
+        }
+        callees
+    }
+
+    fn build_generic(&mut self) {
+        let tcx = self.tcx;
+        for def_id in tcx.hir().body_owners() {
+            if !matches!(tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn | DefKind::Closure) {
+                continue;
+            }
+            let caller = self.node(NodeKey::Item(def_id.to_def_id()));
+            let body = tcx.optimized_mir(def_id);
+            self.add_calls(caller, body, tcx.param_env(def_id), |ty| ty);
+        }
+    }
+
+    fn build_instance(&mut self) {
+        let tcx = self.tcx;
+        let param_env = ty::ParamEnv::reveal_all();
+
+        let mut queue = Vec::new();
+        for def_id in tcx.hir().body_owners() {
+            if tcx
+                .generics_of(tcx.typeck_root_def_id(def_id.to_def_id()))
+                .requires_monomorphization(tcx)
+            {
+                continue;
+            }
+            let instance = match tcx.def_kind(def_id) {
+                DefKind::Fn | DefKind::AssocFn => Instance::mono(tcx, def_id.to_def_id()),
+                DefKind::Closure => match *tcx.type_of(def_id).instantiate_identity().kind() {
+                    ty::Closure(_, args)
+                    | ty::CoroutineClosure(_, args)
+                    | ty::Coroutine(_, args) => {
+                        Instance::new(def_id.to_def_id(), tcx.erase_regions(args))
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            queue.push(instance);
+        }
+
+        let mut seen: FxHashSet<_> = queue.iter().copied().collect();
+        queue.reverse();
+        while let Some(instance) = queue.pop() {
+            let caller = self.node(NodeKey::Instance(instance));
+            let body = tcx.instance_mir(instance.def);
+            let callees = self.add_calls(caller, body, param_env, |ty| {
+                instance.instantiate_mir_and_normalize_erasing_regions(
+                    tcx,
+                    param_env,
+                    ty::EarlyBinder::bind(ty),
+                )
+            });
+            for callee in callees {
+                if let NodeKey::Instance(callee) = callee
+                    && let InstanceDef::Item(def_id) = callee.def
+                    && def_id.is_local()
+                    && tcx.is_mir_available(def_id)
+                    && seen.insert(callee)
+                {
+                    queue.push(callee);
+                }
+            }
+        }
+    }
+}
+
+/// Builds the call graph of the local crate in the given view.
+pub(crate) fn build(tcx: TyCtxt<'_>, view: CallGraphView) -> CallGraph {
+    let mut builder =
+        CallGraphBuilder { tcx, view, nodes: FxIndexMap::default(), edges: Vec::new() };
+    match view {
+        CallGraphView::Generic => builder.build_generic(),
+        CallGraphView::Instance => builder.build_instance(),
+    }
+    CallGraph { nodes: builder.nodes.into_values().collect(), edges: builder.edges }
+}
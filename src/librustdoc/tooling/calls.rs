@@ -0,0 +1,182 @@
+//! Resolution of method calls and calls through paths to the functions they dispatch to.
+
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::DefId;
+use rustc_hir::{BodyId, Expr, ExprKind};
+use rustc_middle::ty::{
+    self, GenericArgKind, GenericArgsRef, Instance, InstanceDef, TyCtxt, TypeVisitableExt,
+    TypeckResults,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{paths, Record, SpanInfo};
+
+/// How a call finds the function it runs.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Dispatch {
+    /// The call always runs the same function body, e.g. an inherent method or the method of
+    /// the one impl that applies.
+    Static,
+    /// The call goes through the vtable of a trait object.
+    Virtual,
+    /// The call is to a compiler intrinsic.
+    Intrinsic,
+    /// The call runs a compiler-generated shim, such as drop glue or `FnOnce::call_once` of a
+    /// closure.
+    Shim,
+    /// The impl depends on the generic parameters of the caller, so the call is only resolved
+    /// once the caller is monomorphized.
+    Generic,
+}
+
+/// The syntax of a call.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CallKind {
+    /// `receiver.method(..)`
+    Method,
+    /// `path::to::function(..)` or `Type::method(..)`
+    Path,
+}
+
+/// A call and what typeck and trait selection resolved it to.
+pub(crate) struct ResolvedCall<'tcx> {
+    pub(crate) kind: CallKind,
+    /// The function named at the call site, e.g. a trait method.
+    pub(crate) callee: DefId,
+    /// The generic arguments of `callee` at the call site.
+    pub(crate) args: GenericArgsRef<'tcx>,
+    pub(crate) dispatch: Dispatch,
+    /// The function that ends up running, if it is known without monomorphizing the caller.
+    pub(crate) target: Option<DefId>,
+}
+
+/// Resolves the call `ex`, if it is a method call or a call through a path to a function.
+pub(crate) fn resolve_call<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    typeck_results: &TypeckResults<'tcx>,
+    param_env: ty::ParamEnv<'tcx>,
+    ex: &Expr<'_>,
+) -> Option<ResolvedCall<'tcx>> {
+    let (kind, callee, args) = match ex.kind {
+        ExprKind::MethodCall(..) => {
+            let (_, def_id) = typeck_results.type_dependent_def(ex.hir_id)?;
+            (CallKind::Method, def_id, typeck_results.node_args(ex.hir_id))
+        }
+        ExprKind::Call(func, _) => {
+            let ExprKind::Path(ref qpath) = func.kind else { return None };
+            match typeck_results.qpath_res(qpath, func.hir_id) {
+                Res::Def(DefKind::Fn | DefKind::AssocFn, def_id) => {
+                    (CallKind::Path, def_id, typeck_results.node_args(func.hir_id))
+                }
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    if args.references_error() {
+        return None;
+    }
+
+    let (dispatch, target) = match Instance::resolve(tcx, param_env, callee, args).ok()? {
+        Some(instance) => match instance.def {
+            InstanceDef::Item(def_id) => (Dispatch::Static, Some(def_id)),
+            InstanceDef::Intrinsic(def_id) => (Dispatch::Intrinsic, Some(def_id)),
+            InstanceDef::Virtual(..) => (Dispatch::Virtual, None),
+            _ => (Dispatch::Shim, Some(instance.def_id())),
+        },
+        None => (Dispatch::Generic, None),
+    };
+    Some(ResolvedCall { kind, callee, args, dispatch, target })
+}
+
+/// Formats the type and const arguments of `args`, leaving out lifetimes.
+pub(crate) fn generic_args_to_strings<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    args: GenericArgsRef<'tcx>,
+) -> Vec<String> {
+    args.iter()
+        .filter(|arg| !matches!(arg.unpack(), GenericArgKind::Lifetime(_)))
+        .map(|arg| paths::print(tcx, arg))
+        .collect()
+}
+
+/// A call and the function it dispatches to.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CallInfo {
+    #[serde(flatten)]
+    span: SpanInfo,
+    kind: CallKind,
+    /// The function named at the call site, e.g. `core::clone::Clone::clone`.
+    callee: String,
+    /// The crate `callee` is defined in.
+    callee_crate: String,
+    /// The trait `callee` is a method of, if any.
+    callee_trait: Option<String>,
+    /// The type and const arguments of `callee` at the call site.
+    generic_args: Vec<String>,
+    dispatch: Dispatch,
+    /// The function that ends up running, e.g. the method of the selected impl, if it is known
+    /// without monomorphizing the caller.
+    target: Option<String>,
+    /// The impl `target` is defined in, if any.
+    target_impl: Option<String>,
+}
+
+struct CallVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    /// Typeck results and `ParamEnv` of the body currently being visited.
+    maybe_body: Option<(&'tcx TypeckResults<'tcx>, ty::ParamEnv<'tcx>)>,
+    records: Vec<Record>,
+}
+
+impl<'tcx> rustc_hir::intravisit::Visitor<'tcx> for CallVisitor<'tcx> {
+    type Map = rustc_middle::hir::map::Map<'tcx>;
+    type NestedFilter = rustc_middle::hir::nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_nested_body(&mut self, body_id: BodyId) {
+        let owner = self.tcx.hir().body_owner_def_id(body_id);
+        let old_body =
+            self.maybe_body.replace((self.tcx.typeck_body(body_id), self.tcx.param_env(owner)));
+        let body = self.tcx.hir().body(body_id);
+        self.visit_body(body);
+        self.maybe_body = old_body;
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx Expr<'tcx>) {
+        if let Some((typeck_results, param_env)) = self.maybe_body
+            && let Some(call) = resolve_call(self.tcx, typeck_results, param_env, ex)
+        {
+            let tcx = self.tcx;
+            let call_info = CallInfo {
+                span: SpanInfo::new(tcx, ex.span),
+                kind: call.kind,
+                callee: paths::def_path(tcx, call.callee),
+                callee_crate: tcx.crate_name(call.callee.krate).to_string(),
+                callee_trait: tcx
+                    .trait_of_item(call.callee)
+                    .map(|def_id| paths::def_path(tcx, def_id)),
+                generic_args: generic_args_to_strings(tcx, call.args),
+                dispatch: call.dispatch,
+                target: call.target.map(|def_id| paths::def_path(tcx, def_id)),
+                target_impl: call
+                    .target
+                    .and_then(|def_id| tcx.impl_of_method(def_id))
+                    .map(|def_id| paths::def_path(tcx, def_id)),
+            };
+            self.records.push(Record::Call(call_info));
+        }
+        rustc_hir::intravisit::walk_expr(self, ex);
+    }
+}
+
+pub(crate) fn collect(tcx: TyCtxt<'_>) -> Vec<Record> {
+    let mut visitor = CallVisitor { tcx, maybe_body: None, records: Vec::new() };
+    tcx.hir().visit_all_item_likes_in_crate(&mut visitor);
+    visitor.records
+}
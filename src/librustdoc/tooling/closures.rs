@@ -0,0 +1,169 @@
+//! Closures, the places they capture and why.
+
+use rustc_ast::CaptureBy;
+use rustc_hir::{BodyId, Expr, ExprKind};
+use rustc_middle::ty::{self, TyCtxt, TypeckResults};
+use serde::{Deserialize, Serialize};
+
+use super::{binding_id, paths, Record, SpanInfo};
+
+/// The `Fn` trait a closure implements at most.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum ClosureKind {
+    Fn,
+    FnMut,
+    FnOnce,
+}
+
+impl From<ty::ClosureKind> for ClosureKind {
+    fn from(kind: ty::ClosureKind) -> Self {
+        match kind {
+            ty::ClosureKind::Fn => ClosureKind::Fn,
+            ty::ClosureKind::FnMut => ClosureKind::FnMut,
+            ty::ClosureKind::FnOnce => ClosureKind::FnOnce,
+        }
+    }
+}
+
+/// How a closure holds a captured place.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureKind {
+    ByValue,
+    /// By shared reference.
+    ByRef,
+    /// By a reference that is immutable but unique, used to mutate through a captured
+    /// `&mut` reference.
+    ByUniqueRef,
+    /// By mutable reference.
+    ByMutRef,
+}
+
+impl From<ty::UpvarCapture> for CaptureKind {
+    fn from(capture: ty::UpvarCapture) -> Self {
+        match capture {
+            ty::UpvarCapture::ByValue => CaptureKind::ByValue,
+            ty::UpvarCapture::ByRef(ty::BorrowKind::ImmBorrow) => CaptureKind::ByRef,
+            ty::UpvarCapture::ByRef(ty::BorrowKind::UniqueImmBorrow) => CaptureKind::ByUniqueRef,
+            ty::UpvarCapture::ByRef(ty::BorrowKind::MutBorrow) => CaptureKind::ByMutRef,
+        }
+    }
+}
+
+/// A place captured by a closure.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CaptureInfo {
+    /// The captured place, e.g. `self.buf.len`.
+    place: String,
+    /// The id of the variable the place is rooted at.
+    binding: String,
+    ty: String,
+    kind: CaptureKind,
+    /// The use of the place that required `kind`.
+    kind_span: SpanInfo,
+    /// The use of the place that required capturing this exact place.
+    path_span: SpanInfo,
+}
+
+/// The use of a captured place that made a closure `FnMut` or `FnOnce`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KindOriginInfo {
+    place: String,
+    span: SpanInfo,
+}
+
+/// A closure and its captures.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClosureInfo {
+    #[serde(flatten)]
+    span: SpanInfo,
+    /// Whether the closure is declared with `move`.
+    is_move: bool,
+    kind: Option<ClosureKind>,
+    kind_origin: Option<KindOriginInfo>,
+    captures: Vec<CaptureInfo>,
+}
+
+struct ClosureVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    maybe_typeck_results: Option<&'tcx TypeckResults<'tcx>>,
+    records: Vec<Record>,
+}
+
+impl<'tcx> ClosureVisitor<'tcx> {
+    fn record_closure(
+        &mut self,
+        ex: &'tcx Expr<'tcx>,
+        closure: &'tcx rustc_hir::Closure<'tcx>,
+        typeck_results: &'tcx TypeckResults<'tcx>,
+    ) {
+        let tcx = self.tcx;
+        let kind = typeck_results.node_type_opt(ex.hir_id).and_then(|ty| match *ty.kind() {
+            ty::Closure(_, args) => args.as_closure().kind_ty().to_opt_closure_kind(),
+            ty::CoroutineClosure(_, args) => {
+                args.as_coroutine_closure().kind_ty().to_opt_closure_kind()
+            }
+            _ => None,
+        });
+        let kind_origin =
+            typeck_results.closure_kind_origins().get(ex.hir_id).map(|(span, place)| {
+                KindOriginInfo {
+                    place: ty::place_to_string_for_capture(tcx, place),
+                    span: SpanInfo::new(tcx, *span),
+                }
+            });
+        let captures = typeck_results
+            .closure_min_captures_flattened(closure.def_id)
+            .map(|capture| CaptureInfo {
+                place: capture.to_string(tcx),
+                binding: binding_id(tcx, capture.get_root_variable()),
+                ty: paths::print(tcx, capture.place.ty()),
+                kind: capture.info.capture_kind.into(),
+                kind_span: SpanInfo::new(tcx, capture.get_capture_kind_span(tcx)),
+                path_span: SpanInfo::new(tcx, capture.get_path_span(tcx)),
+            })
+            .collect();
+
+        let closure_info = ClosureInfo {
+            span: SpanInfo::new(tcx, ex.span),
+            is_move: matches!(closure.capture_clause, CaptureBy::Value { .. }),
+            kind: kind.map(ClosureKind::from),
+            kind_origin,
+            captures,
+        };
+        self.records.push(Record::Closure(closure_info));
+    }
+}
+
+impl<'tcx> rustc_hir::intravisit::Visitor<'tcx> for ClosureVisitor<'tcx> {
+    type Map = rustc_middle::hir::map::Map<'tcx>;
+    type NestedFilter = rustc_middle::hir::nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_nested_body(&mut self, body_id: BodyId) {
+        let old_typeck_results = self.maybe_typeck_results.replace(self.tcx.typeck_body(body_id));
+        let body = self.tcx.hir().body(body_id);
+        self.visit_body(body);
+        self.maybe_typeck_results = old_typeck_results;
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx Expr<'tcx>) {
+        // Coroutines, such as `async` blocks, capture like closures but are not called.
+        if let ExprKind::Closure(closure) = ex.kind
+            && !matches!(closure.kind, rustc_hir::ClosureKind::Coroutine(_))
+            && let Some(typeck_results) = self.maybe_typeck_results
+        {
+            self.record_closure(ex, closure, typeck_results);
+        }
+        rustc_hir::intravisit::walk_expr(self, ex);
+    }
+}
+
+pub(crate) fn collect(tcx: TyCtxt<'_>) -> Vec<Record> {
+    let mut visitor = ClosureVisitor { tcx, maybe_typeck_results: None, records: Vec::new() };
+    tcx.hir().visit_all_item_likes_in_crate(&mut visitor);
+    visitor.records
+}
@@ -0,0 +1,283 @@
+//! Command-line configuration for the tooling analyses.
+
+use std::path::PathBuf;
+
+use rustc_errors::ErrorGuaranteed;
+use rustc_session::config::CrateType;
+use rustc_session::getopts;
+
+/// The file format the tooling results are written in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum ToolingFormat {
+    /// A single JSON array holding every record.
+    #[default]
+    Json,
+    /// One JSON object per line.
+    Jsonl,
+    /// Comma-separated values, one record per row.
+    Csv,
+}
+
+impl ToolingFormat {
+    /// The file extension used for result files in this format.
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            ToolingFormat::Json => "json",
+            ToolingFormat::Jsonl => "jsonl",
+            ToolingFormat::Csv => "csv",
+        }
+    }
+}
+
+impl TryFrom<&str> for ToolingFormat {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "json" => Ok(ToolingFormat::Json),
+            "jsonl" => Ok(ToolingFormat::Jsonl),
+            "csv" => Ok(ToolingFormat::Csv),
+            _ => Err(format!("unknown tooling format `{value}`")),
+        }
+    }
+}
+
+/// How types and the paths of items are printed in the tooling results.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum PathStyle {
+    /// Only the names of items, e.g. `Vec<String>`.
+    Trimmed,
+    /// Full paths, with items of the analyzed crate starting with `crate::`.
+    CrateRelative,
+    /// Full paths, e.g. `std::vec::Vec<std::string::String>`.
+    #[default]
+    FullyQualified,
+    /// The `DefPath` of items, prefixed with their crate name and `StableCrateId`.
+    Canonical,
+}
+
+impl TryFrom<&str> for PathStyle {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "trimmed" => Ok(PathStyle::Trimmed),
+            "crate-relative" => Ok(PathStyle::CrateRelative),
+            "full" => Ok(PathStyle::FullyQualified),
+            "canonical" => Ok(PathStyle::Canonical),
+            _ => Err(format!("unknown tooling path style `{value}`")),
+        }
+    }
+}
+
+/// An analysis run by the tooling binary. Each analysis writes its own result file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum ToolingAnalysis {
+    /// Variable bindings, their uses and assignments to them, and the bounds in scope.
+    Vars,
+    /// Types and adjustments of every expression.
+    Exprs,
+    /// The functions method calls and calls through paths dispatch to.
+    Calls,
+    /// The call graph of the crate, with one node per function.
+    CallGraph,
+    /// The call graph of the crate, with one node per monomorphized instance.
+    MonoCallGraph,
+    /// Closures, their kind and the places they capture.
+    Closures,
+    /// Moves, copies, borrows and drops of variables, from the MIR borrowck works on.
+    Borrows,
+    /// The source ranges where variables and their storage are live.
+    Liveness,
+    /// Drop points and drop order, from MIR after drop elaboration.
+    Drops,
+    /// `unsafe` blocks, functions, impls and traits, and the unsafe operations they contain.
+    Unsafe,
+    /// Places where functions can panic, explicitly or implicitly.
+    Panics,
+    /// The `.await` points of `async` code and the values held across them.
+    Coroutines,
+    /// The concrete types and sizes of the locals of every monomorphized instance, to find the
+    /// instantiations with huge types or stack frames.
+    MonoLocals,
+}
+
+impl ToolingAnalysis {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ToolingAnalysis::Vars => "vars",
+            ToolingAnalysis::Exprs => "exprs",
+            ToolingAnalysis::Calls => "calls",
+            ToolingAnalysis::CallGraph => "callgraph",
+            ToolingAnalysis::MonoCallGraph => "mono-callgraph",
+            ToolingAnalysis::Closures => "closures",
+            ToolingAnalysis::Borrows => "borrows",
+            ToolingAnalysis::Liveness => "liveness",
+            ToolingAnalysis::Drops => "drops",
+            ToolingAnalysis::Unsafe => "unsafe",
+            ToolingAnalysis::Panics => "panics",
+            ToolingAnalysis::Coroutines => "coroutines",
+            ToolingAnalysis::MonoLocals => "mono-locals",
+        }
+    }
+
+    /// Whether the analysis reads the MIR bodies borrowck works on, which must be kept aside
+    /// by overriding `mir_borrowck`.
+    pub(crate) fn needs_borrowck_bodies(self) -> bool {
+        matches!(self, ToolingAnalysis::Borrows | ToolingAnalysis::Liveness)
+    }
+}
+
+impl TryFrom<&str> for ToolingAnalysis {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "vars" => Ok(ToolingAnalysis::Vars),
+            "exprs" => Ok(ToolingAnalysis::Exprs),
+            "calls" => Ok(ToolingAnalysis::Calls),
+            "callgraph" => Ok(ToolingAnalysis::CallGraph),
+            "mono-callgraph" => Ok(ToolingAnalysis::MonoCallGraph),
+            "closures" => Ok(ToolingAnalysis::Closures),
+            "borrows" => Ok(ToolingAnalysis::Borrows),
+            "liveness" => Ok(ToolingAnalysis::Liveness),
+            "drops" => Ok(ToolingAnalysis::Drops),
+            "unsafe" => Ok(ToolingAnalysis::Unsafe),
+            "panics" => Ok(ToolingAnalysis::Panics),
+            "coroutines" => Ok(ToolingAnalysis::Coroutines),
+            "mono-locals" => Ok(ToolingAnalysis::MonoLocals),
+            _ => Err(format!("unknown tooling analysis `{value}`")),
+        }
+    }
+}
+
+/// Options controlling what the tooling analyses emit and where.
+#[derive(Clone, Debug)]
+pub(crate) struct ToolingOptions {
+    /// Directory the result files are written to. Defaults to the current directory.
+    pub(crate) out_dir: PathBuf,
+    /// Format of the result files.
+    pub(crate) format: ToolingFormat,
+    /// Whether to stop after the tooling analyses instead of also generating documentation.
+    pub(crate) analysis_only: bool,
+    /// The analyses to run, in order. Defaults to `vars`.
+    pub(crate) analyses: Vec<ToolingAnalysis>,
+    /// Whether to leave out records located in code produced by macro expansions.
+    pub(crate) skip_expansions: bool,
+    /// How types and paths are printed.
+    pub(crate) path_style: PathStyle,
+    /// Whether to also emit the hidden types of opaque types, such as `impl Trait` types and
+    /// the futures of `async fn`s.
+    pub(crate) reveal_opaque_types: bool,
+    /// Paths of traits to check variable types against, besides the auto traits and the
+    /// common derivable traits.
+    pub(crate) extra_traits: Vec<String>,
+}
+
+impl ToolingOptions {
+    pub(crate) fn new(
+        matches: &getopts::Matches,
+        dcx: &rustc_errors::DiagCtxt,
+    ) -> Result<Self, ErrorGuaranteed> {
+        let out_dir = matches.opt_str("tooling-out-dir").map(PathBuf::from).unwrap_or_default();
+        let format = match matches.opt_str("tooling-format") {
+            Some(s) => match ToolingFormat::try_from(s.as_str()) {
+                Ok(format) => format,
+                Err(e) => return Err(dcx.err(e)),
+            },
+            None => ToolingFormat::default(),
+        };
+        let analysis_only = matches.opt_present("tooling-analysis-only");
+        let skip_expansions = matches.opt_present("tooling-skip-expansions");
+        let reveal_opaque_types = matches.opt_present("tooling-reveal-opaque-types");
+        let extra_traits = matches
+            .opt_strs("tooling-trait")
+            .iter()
+            .flat_map(|list| list.split(','))
+            .filter(|path| !path.is_empty())
+            .map(|path| path.to_string())
+            .collect();
+        let path_style = match matches.opt_str("tooling-path-style") {
+            Some(s) => match PathStyle::try_from(s.as_str()) {
+                Ok(path_style) => path_style,
+                Err(e) => return Err(dcx.err(e)),
+            },
+            None => PathStyle::default(),
+        };
+
+        let analyses = match parse_analyses(&matches.opt_strs("tooling-analysis")) {
+            Ok(analyses) => analyses,
+            Err(e) => return Err(dcx.err(e)),
+        };
+
+        Ok(ToolingOptions {
+            out_dir,
+            format,
+            analysis_only,
+            analyses,
+            skip_expansions,
+            path_style,
+            reveal_opaque_types,
+            extra_traits,
+        })
+    }
+
+    /// Returns the path of the result file of `analysis` with the given extension for the crate
+    /// identified by `crate_file_stem`.
+    pub(crate) fn output_path(
+        &self,
+        crate_file_stem: &str,
+        analysis: ToolingAnalysis,
+        extension: &str,
+    ) -> PathBuf {
+        self.out_dir.join(format!("{crate_file_stem}.{}.{extension}", analysis.name()))
+    }
+}
+
+/// Parses the comma-separated lists of analyses given with `--tooling-analysis`, dropping
+/// duplicates. Defaults to `vars` when no analysis is given.
+fn parse_analyses(lists: &[String]) -> Result<Vec<ToolingAnalysis>, String> {
+    let mut analyses = Vec::new();
+    for list in lists {
+        for name in list.split(',') {
+            let analysis = ToolingAnalysis::try_from(name)?;
+            if !analyses.contains(&analysis) {
+                analyses.push(analysis);
+            }
+        }
+    }
+    if analyses.is_empty() {
+        analyses.push(ToolingAnalysis::Vars);
+    }
+    Ok(analyses)
+}
+
+/// Returns the start of the names of the result files of a crate.
+///
+/// Every crate gets its own files so that analyzing a whole workspace does not overwrite the
+/// results of previously analyzed members. The lib, bins and tests of a package share their
+/// crate name, so the crate type and the `StableCrateId`, which depends on `-C metadata`, are
+/// part of the name as well.
+pub(crate) fn crate_file_stem(
+    crate_name: &str,
+    crate_type: CrateType,
+    stable_crate_id: u64,
+    target: &str,
+) -> String {
+    format!("{crate_name}-{crate_type}-{stable_crate_id:016x}-{}", sanitize(target))
+}
+
+/// Replaces characters that cannot appear in a file name, which can happen when the target
+/// is given as a path to a target specification file.
+fn sanitize(target: &str) -> String {
+    target
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
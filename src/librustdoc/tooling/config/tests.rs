@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+
+use rustc_session::config::CrateType;
+
+use super::{
+    crate_file_stem, parse_analyses, sanitize, PathStyle, ToolingAnalysis, ToolingFormat,
+    ToolingOptions,
+};
+
+fn options(out_dir: &str) -> ToolingOptions {
+    ToolingOptions {
+        out_dir: PathBuf::from(out_dir),
+        format: ToolingFormat::Json,
+        analysis_only: false,
+        analyses: vec![ToolingAnalysis::Vars],
+        skip_expansions: false,
+        path_style: PathStyle::default(),
+        reveal_opaque_types: false,
+        extra_traits: Vec::new(),
+    }
+}
+
+fn lists(lists: &[&str]) -> Vec<String> {
+    lists.iter().map(|list| list.to_string()).collect()
+}
+
+#[test]
+fn analysis_names_round_trip() {
+    for analysis in [ToolingAnalysis::Vars, ToolingAnalysis::Exprs] {
+        assert_eq!(ToolingAnalysis::try_from(analysis.name()), Ok(analysis));
+    }
+}
+
+#[test]
+fn unknown_analysis_is_rejected() {
+    assert_eq!(
+        ToolingAnalysis::try_from("Vars"),
+        Err("unknown tooling analysis `Vars`".to_string())
+    );
+    assert!(parse_analyses(&lists(&["vars,typo"])).is_err());
+    // A trailing comma leaves an empty name.
+    assert!(parse_analyses(&lists(&["vars,"])).is_err());
+}
+
+#[test]
+fn analyses_default_to_vars() {
+    assert_eq!(parse_analyses(&[]), Ok(vec![ToolingAnalysis::Vars]));
+}
+
+#[test]
+fn analyses_keep_order_and_drop_duplicates() {
+    assert_eq!(
+        parse_analyses(&lists(&["exprs,vars", "vars,exprs"])),
+        Ok(vec![ToolingAnalysis::Exprs, ToolingAnalysis::Vars])
+    );
+}
+
+#[test]
+fn format_parsing() {
+    assert_eq!(ToolingFormat::try_from("json"), Ok(ToolingFormat::Json));
+    assert_eq!(ToolingFormat::try_from("jsonl"), Ok(ToolingFormat::Jsonl));
+    assert_eq!(ToolingFormat::try_from("csv"), Ok(ToolingFormat::Csv));
+    assert_eq!(ToolingFormat::try_from("xml"), Err("unknown tooling format `xml`".to_string()));
+    assert_eq!(ToolingFormat::default().extension(), "json");
+}
+
+#[test]
+fn path_style_parsing() {
+    assert_eq!(PathStyle::try_from("trimmed"), Ok(PathStyle::Trimmed));
+    assert_eq!(PathStyle::try_from("crate-relative"), Ok(PathStyle::CrateRelative));
+    assert_eq!(PathStyle::try_from("full"), Ok(PathStyle::FullyQualified));
+    assert_eq!(PathStyle::try_from("canonical"), Ok(PathStyle::Canonical));
+    assert!(PathStyle::try_from("fully-qualified").is_err());
+}
+
+#[test]
+fn sanitize_keeps_target_triples() {
+    assert_eq!(sanitize("x86_64-unknown-linux-gnu"), "x86_64-unknown-linux-gnu");
+    assert_eq!(sanitize("thumbv7em-none-eabihf"), "thumbv7em-none-eabihf");
+}
+
+#[test]
+fn sanitize_replaces_separators() {
+    assert_eq!(sanitize("/targets/my-target.json"), "_targets_my-target.json");
+    assert_eq!(sanitize(r"C:\targets\my-target.json"), "C__targets_my-target.json");
+    assert_eq!(sanitize("my target"), "my_target");
+}
+
+#[test]
+fn sanitize_parent_dirs_stay_in_the_file_name() {
+    assert_eq!(sanitize("../../etc/target.json"), ".._.._etc_target.json");
+}
+
+#[test]
+fn crate_file_stem_tells_crate_types_apart() {
+    let target = "x86_64-unknown-linux-gnu";
+    assert_eq!(
+        crate_file_stem("foo", CrateType::Rlib, 0x1234, target),
+        "foo-rlib-0000000000001234-x86_64-unknown-linux-gnu"
+    );
+    assert_ne!(
+        crate_file_stem("foo", CrateType::Rlib, 0x1234, target),
+        crate_file_stem("foo", CrateType::Executable, 0x1234, target)
+    );
+    // The test harness of a bin is a bin as well, built with another `-C metadata`.
+    assert_ne!(
+        crate_file_stem("foo", CrateType::Executable, 0x1234, target),
+        crate_file_stem("foo", CrateType::Executable, 0x5678, target)
+    );
+}
+
+#[test]
+fn output_path_is_in_out_dir() {
+    let options = options("results");
+    let stem = crate_file_stem("foo", CrateType::Rlib, 0x1234, "x86_64-unknown-linux-gnu");
+    assert_eq!(
+        options.output_path(&stem, ToolingAnalysis::CallGraph, "dot"),
+        Path::new("results/foo-rlib-0000000000001234-x86_64-unknown-linux-gnu.callgraph.dot")
+    );
+
+    for target in ["../../escape", "/abs/target.json", "..", ""] {
+        let stem = crate_file_stem("foo", CrateType::Rlib, 0x1234, target);
+        let path = options.output_path(&stem, ToolingAnalysis::Vars, "json");
+        assert_eq!(path.parent(), Some(Path::new("results")), "{target:?}");
+    }
+}
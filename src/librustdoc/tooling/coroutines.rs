@@ -0,0 +1,154 @@
+//! Suspension points of `async` blocks and functions and of coroutines, and the values the
+//! state machine keeps across them.
+
+use rustc_hir::{CoroutineDesugaring, CoroutineKind as HirCoroutineKind, CoroutineSource};
+use rustc_middle::mir::CoroutineLayout;
+use rustc_middle::ty::{self, CoroutineArgs, ParamEnv, Ty, TyCtxt};
+use rustc_span::def_id::LocalDefId;
+use rustc_span::{sym, DesugaringKind};
+use rustc_target::abi::VariantIdx;
+use serde::{Deserialize, Serialize};
+
+use super::traits::implements_trait;
+use super::{paths, Record, SpanInfo};
+
+/// The construct a coroutine comes from.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CoroutineKind {
+    AsyncFn,
+    AsyncBlock,
+    AsyncClosure,
+    Gen,
+    AsyncGen,
+    /// A closure containing `yield`.
+    Coroutine,
+}
+
+impl From<HirCoroutineKind> for CoroutineKind {
+    fn from(kind: HirCoroutineKind) -> Self {
+        match kind {
+            HirCoroutineKind::Desugared(CoroutineDesugaring::Async, CoroutineSource::Fn) => {
+                CoroutineKind::AsyncFn
+            }
+            HirCoroutineKind::Desugared(CoroutineDesugaring::Async, CoroutineSource::Block) => {
+                CoroutineKind::AsyncBlock
+            }
+            HirCoroutineKind::Desugared(CoroutineDesugaring::Async, CoroutineSource::Closure) => {
+                CoroutineKind::AsyncClosure
+            }
+            HirCoroutineKind::Desugared(CoroutineDesugaring::Gen, _) => CoroutineKind::Gen,
+            HirCoroutineKind::Desugared(CoroutineDesugaring::AsyncGen, _) => {
+                CoroutineKind::AsyncGen
+            }
+            HirCoroutineKind::Coroutine(_) => CoroutineKind::Coroutine,
+        }
+    }
+}
+
+/// A value stored in the state machine while it is suspended.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HeldValueInfo {
+    /// The name of the variable, or `None` for temporaries.
+    name: Option<String>,
+    span: SpanInfo,
+    ty: String,
+    /// The size in bytes, if the layout of the type can be computed.
+    size: Option<u64>,
+    /// Whether the type is `Send`. A future that holds a value that is not `Send` across an
+    /// `.await` is not `Send` either.
+    send: bool,
+}
+
+/// An `.await` or a `yield`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuspensionPointInfo {
+    span: SpanInfo,
+    /// Whether this is an `.await` rather than a `yield`.
+    is_await: bool,
+    /// The values held across this point.
+    held: Vec<HeldValueInfo>,
+}
+
+/// An `async` block or function, or a coroutine.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CoroutineInfo {
+    #[serde(flatten)]
+    span: SpanInfo,
+    /// The path of the coroutine, e.g. `run::{closure#0}` for the body of `async fn run`.
+    coroutine: String,
+    kind: CoroutineKind,
+    /// The size in bytes of the whole state machine, if it can be computed.
+    size: Option<u64>,
+    suspension_points: Vec<SuspensionPointInfo>,
+}
+
+fn size_of<'tcx>(tcx: TyCtxt<'tcx>, param_env: ParamEnv<'tcx>, ty: Ty<'tcx>) -> Option<u64> {
+    tcx.layout_of(param_env.and(ty)).ok().map(|layout| layout.size.bytes())
+}
+
+fn suspension_points<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    layout: &CoroutineLayout<'tcx>,
+) -> Vec<SuspensionPointInfo> {
+    let send = tcx.get_diagnostic_item(sym::Send);
+    // The first variants are for the unresumed, returned and poisoned states; the others are
+    // for the suspension points.
+    let first_suspension_point = VariantIdx::from_usize(CoroutineArgs::POISONED + 1);
+    layout
+        .variant_fields
+        .iter_enumerated()
+        .filter(|&(variant, _)| variant >= first_suspension_point)
+        .map(|(variant, fields)| {
+            let span = layout.variant_source_info[variant].span;
+            let held = fields
+                .iter()
+                .map(|&local| {
+                    let saved = &layout.field_tys[local];
+                    HeldValueInfo {
+                        name: layout.field_names[local].map(|name| name.to_string()),
+                        span: SpanInfo::new(tcx, saved.source_info.span),
+                        ty: paths::print(tcx, saved.ty),
+                        size: size_of(tcx, param_env, saved.ty),
+                        send: send
+                            .is_some_and(|send| implements_trait(tcx, param_env, saved.ty, send)),
+                    }
+                })
+                .collect();
+            SuspensionPointInfo {
+                span: SpanInfo::new(tcx, span),
+                is_await: span.is_desugaring(DesugaringKind::Await),
+                held,
+            }
+        })
+        .collect()
+}
+
+fn collect_coroutine(tcx: TyCtxt<'_>, def_id: LocalDefId, kind: HirCoroutineKind) -> Record {
+    let param_env = tcx.param_env_reveal_all_normalized(def_id);
+    let coroutine_ty = tcx.type_of(def_id).instantiate_identity();
+    let coroutine_info = CoroutineInfo {
+        span: SpanInfo::new(tcx, tcx.def_span(def_id)),
+        coroutine: paths::def_path(tcx, def_id.to_def_id()),
+        kind: kind.into(),
+        size: size_of(tcx, param_env, coroutine_ty),
+        suspension_points: tcx
+            .coroutine_layout(def_id.to_def_id())
+            .map(|layout| suspension_points(tcx, param_env, layout))
+            .unwrap_or_default(),
+    };
+    Record::Coroutine(coroutine_info)
+}
+
+pub(crate) fn collect(tcx: TyCtxt<'_>) -> Vec<Record> {
+    tcx.hir()
+        .body_owners()
+        .filter_map(|def_id| {
+            let kind = tcx.coroutine_kind(def_id)?;
+            // The coroutine's type is not a coroutine if type checking failed.
+            matches!(tcx.type_of(def_id).instantiate_identity().kind(), ty::Coroutine(..))
+                .then(|| collect_coroutine(tcx, def_id, kind))
+        })
+        .collect()
+}
@@ -0,0 +1,167 @@
+//! Drop points and drop order, read from the optimized MIR, which is built after drop
+//! elaboration.
+
+use rustc_hir::def::DefKind;
+use rustc_index::bit_set::BitSet;
+use rustc_middle::mir::{
+    BasicBlock, Body, ClearCrossCrate, LocalInfo, StatementKind, TerminatorKind,
+};
+use rustc_middle::ty::{self, Ty, TyCtxt};
+use serde::{Deserialize, Serialize};
+
+use super::places::{place_to_string, UserVars};
+use super::{paths, Record, SpanInfo};
+
+/// What a dropped place belongs to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum DroppedKind {
+    /// A user variable, or a place rooted at one.
+    Variable,
+    /// A temporary of the tail expression of a block, which lives until the end of the
+    /// statement enclosing the block.
+    TailTemporary,
+    /// Another temporary, usually dropped at the end of the enclosing statement.
+    Temporary,
+}
+
+/// A `Drop` terminator.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DropInfo {
+    #[serde(flatten)]
+    span: SpanInfo,
+    /// The function or closure the drop happens in.
+    function: String,
+    /// The dropped place, named after the variable it is rooted at. Temporaries have no name,
+    /// and are found from `created_at`.
+    place: Option<String>,
+    dropped: DroppedKind,
+    /// Where the dropped variable is declared, or where the dropped temporary is created.
+    created_at: SpanInfo,
+    ty: String,
+    needs_drop: bool,
+    /// The `Drop::drop` implementation that runs first, if the type implements `Drop`. Drop
+    /// glue then drops the fields.
+    drop_impl: Option<String>,
+    /// Whether the drop is on an unwind path.
+    unwind: bool,
+    /// Whether the drop happens because the place is assigned a new value.
+    on_assignment: bool,
+    /// Drops running one after the other, like at the end of a scope, share a chain.
+    chain: usize,
+    /// The position of the drop in its chain.
+    order: usize,
+}
+
+fn drop_impl<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Option<String> {
+    match ty.kind() {
+        ty::Adt(def, _) => tcx.adt_destructor(def.did()).map(|dtor| paths::def_path(tcx, dtor.did)),
+        _ => None,
+    }
+}
+
+/// Returns the target of the `Drop` terminator of `block` if it is another block that only
+/// drops, and is reached from nowhere else.
+///
+/// Storage markers are not removed from the optimized MIR of every build, e.g. not when the
+/// crate is built with optimizations, so the target may also end the storage of locals.
+fn next_in_chain(
+    body: &Body<'_>,
+    drop_blocks: &BitSet<BasicBlock>,
+    block: BasicBlock,
+) -> Option<BasicBlock> {
+    let TerminatorKind::Drop { target, .. } = body.basic_blocks[block].terminator().kind else {
+        return None;
+    };
+    (drop_blocks.contains(target)
+        && body.basic_blocks[target].statements.iter().all(|statement| {
+            matches!(
+                statement.kind,
+                StatementKind::StorageLive(_) | StatementKind::StorageDead(_) | StatementKind::Nop
+            )
+        })
+        && body.basic_blocks.predecessors()[target].len() == 1)
+        .then_some(target)
+}
+
+fn collect_body<'tcx>(tcx: TyCtxt<'tcx>, body: &Body<'tcx>, records: &mut Vec<Record>) {
+    let def_id = body.source.def_id();
+    let function = paths::def_path(tcx, def_id);
+    let param_env = tcx.param_env(def_id);
+    let vars = UserVars::new(body);
+
+    let mut drop_blocks = BitSet::new_empty(body.basic_blocks.len());
+    for (block, data) in body.basic_blocks.iter_enumerated() {
+        let terminator = data.terminator();
+        // Drops inlined from callees belong to the callees.
+        if let TerminatorKind::Drop { .. } = terminator.kind
+            && terminator.source_info.scope.inlined_instance(&body.source_scopes).is_none()
+        {
+            drop_blocks.insert(block);
+        }
+    }
+    let mut continued = BitSet::new_empty(body.basic_blocks.len());
+    for block in drop_blocks.iter() {
+        if let Some(next) = next_in_chain(body, &drop_blocks, block) {
+            continued.insert(next);
+        }
+    }
+
+    let mut chain = 0;
+    for start in drop_blocks.iter().filter(|&block| !continued.contains(block)) {
+        let mut order = 0;
+        let mut next = Some(start);
+        while let Some(block) = next {
+            let data = &body.basic_blocks[block];
+            let terminator = data.terminator();
+            let TerminatorKind::Drop { place, replace, .. } = terminator.kind else { break };
+
+            let ty = place.ty(body, tcx).ty;
+            let local_decl = &body.local_decls[place.local];
+            let (name, dropped, created_at) = match vars.root_of(place) {
+                Some(var) => {
+                    (Some(place_to_string(tcx, body, var, place)), DroppedKind::Variable, var.span)
+                }
+                None => {
+                    let dropped = if let ClearCrossCrate::Set(info) = &local_decl.local_info
+                        && let LocalInfo::BlockTailTemp(_) = **info
+                    {
+                        DroppedKind::TailTemporary
+                    } else {
+                        DroppedKind::Temporary
+                    };
+                    (None, dropped, local_decl.source_info.span)
+                }
+            };
+            let drop_info = DropInfo {
+                span: SpanInfo::new(tcx, terminator.source_info.span),
+                function: function.clone(),
+                place: name,
+                dropped,
+                created_at: SpanInfo::new(tcx, created_at),
+                ty: paths::print(tcx, ty),
+                needs_drop: ty.needs_drop(tcx, param_env),
+                drop_impl: drop_impl(tcx, ty),
+                unwind: data.is_cleanup,
+                on_assignment: replace,
+                chain,
+                order,
+            };
+            records.push(Record::Drop(drop_info));
+
+            order += 1;
+            next = next_in_chain(body, &drop_blocks, block);
+        }
+        chain += 1;
+    }
+}
+
+pub(crate) fn collect(tcx: TyCtxt<'_>) -> Vec<Record> {
+    let mut records = Vec::new();
+    for def_id in tcx.hir().body_owners() {
+        if matches!(tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn | DefKind::Closure) {
+            collect_body(tcx, tcx.optimized_mir(def_id), &mut records);
+        }
+    }
+    records
+}
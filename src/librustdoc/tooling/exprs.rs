@@ -0,0 +1,132 @@
+//! Types of expressions, before and after the adjustments typeck applied to them.
+
+use rustc_hir::{BodyId, Expr, Mutability};
+use rustc_middle::ty::adjustment::{Adjust, Adjustment, AutoBorrow, PointerCoercion};
+use rustc_middle::ty::{TyCtxt, TypeckResults};
+use serde::{Deserialize, Serialize};
+
+use super::{paths, Record, SpanInfo};
+
+/// One implicit step applied to the value of an expression.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AdjustKind {
+    /// `!` coerced to any other type.
+    NeverToAny,
+    /// A built-in dereference, e.g. of a reference in an autoderef or reborrow.
+    Deref,
+    /// A `Deref::deref` or `DerefMut::deref_mut` call.
+    OverloadedDeref { mutable: bool },
+    /// An autoref, taking `&` or `&mut` of the value.
+    Borrow { mutable: bool },
+    /// Taking a raw pointer to the value.
+    RawBorrow { mutable: bool },
+    /// A pointer coercion such as `unsize` or `reify_fn_pointer`.
+    Pointer { coercion: String },
+    /// A cast into a `dyn*` object.
+    DynStar,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AdjustmentInfo {
+    #[serde(flatten)]
+    kind: AdjustKind,
+    /// The type of the value after this step.
+    target: String,
+}
+
+impl AdjustmentInfo {
+    fn new<'tcx>(tcx: TyCtxt<'tcx>, adjustment: &Adjustment<'tcx>) -> Self {
+        let kind = match adjustment.kind {
+            Adjust::NeverToAny => AdjustKind::NeverToAny,
+            Adjust::Deref(None) => AdjustKind::Deref,
+            Adjust::Deref(Some(deref)) => {
+                AdjustKind::OverloadedDeref { mutable: deref.mutbl.is_mut() }
+            }
+            Adjust::Borrow(AutoBorrow::Ref(_, mutbl)) => {
+                AdjustKind::Borrow { mutable: Mutability::from(mutbl).is_mut() }
+            }
+            Adjust::Borrow(AutoBorrow::RawPtr(mutbl)) => {
+                AdjustKind::RawBorrow { mutable: mutbl.is_mut() }
+            }
+            Adjust::Pointer(coercion) => {
+                AdjustKind::Pointer { coercion: coercion_name(coercion).to_string() }
+            }
+            Adjust::DynStar => AdjustKind::DynStar,
+        };
+        AdjustmentInfo { kind, target: paths::print(tcx, adjustment.target) }
+    }
+}
+
+fn coercion_name(coercion: PointerCoercion) -> &'static str {
+    match coercion {
+        PointerCoercion::ReifyFnPointer => "reify_fn_pointer",
+        PointerCoercion::UnsafeFnPointer => "unsafe_fn_pointer",
+        PointerCoercion::ClosureFnPointer(_) => "closure_fn_pointer",
+        PointerCoercion::MutToConstPointer => "mut_to_const_pointer",
+        PointerCoercion::ArrayToPointer => "array_to_pointer",
+        PointerCoercion::Unsize => "unsize",
+    }
+}
+
+/// The type of an expression in a body.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExprInfo {
+    #[serde(flatten)]
+    span: SpanInfo,
+    /// The type of the expression as written.
+    ty: Option<String>,
+    /// The type of the expression once all adjustments are applied.
+    adjusted_ty: Option<String>,
+    /// The adjustments applied to the expression, in order.
+    adjustments: Vec<AdjustmentInfo>,
+}
+
+struct ExprVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    /// Typeck results of the body currently being visited.
+    maybe_typeck_results: Option<&'tcx TypeckResults<'tcx>>,
+    records: Vec<Record>,
+}
+
+impl<'tcx> rustc_hir::intravisit::Visitor<'tcx> for ExprVisitor<'tcx> {
+    type Map = rustc_middle::hir::map::Map<'tcx>;
+    type NestedFilter = rustc_middle::hir::nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_nested_body(&mut self, body_id: BodyId) {
+        let old_typeck_results = self.maybe_typeck_results.replace(self.tcx.typeck_body(body_id));
+        let body = self.tcx.hir().body(body_id);
+        self.visit_body(body);
+        self.maybe_typeck_results = old_typeck_results;
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx Expr<'tcx>) {
+        if let Some(typeck_results) = self.maybe_typeck_results {
+            let tcx = self.tcx;
+            let expr_info = ExprInfo {
+                span: SpanInfo::new(tcx, ex.span),
+                ty: typeck_results.expr_ty_opt(ex).map(|ty| paths::print(tcx, ty)),
+                adjusted_ty: typeck_results
+                    .expr_ty_adjusted_opt(ex)
+                    .map(|ty| paths::print(tcx, ty)),
+                adjustments: typeck_results
+                    .expr_adjustments(ex)
+                    .iter()
+                    .map(|adjustment| AdjustmentInfo::new(tcx, adjustment))
+                    .collect(),
+            };
+            self.records.push(Record::Expr(expr_info));
+        }
+        rustc_hir::intravisit::walk_expr(self, ex);
+    }
+}
+
+pub(crate) fn collect(tcx: TyCtxt<'_>) -> Vec<Record> {
+    let mut visitor = ExprVisitor { tcx, maybe_typeck_results: None, records: Vec::new() };
+    tcx.hir().visit_all_item_likes_in_crate(&mut visitor);
+    visitor.records
+}
@@ -0,0 +1,55 @@
+//! The bounds in scope in generic bodies, which give meaning to generic types like `T` or
+//! `<I as Iterator>::Item`.
+
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::TyCtxt;
+use serde::{Deserialize, Serialize};
+
+use super::{paths, SpanInfo};
+
+/// A where clause or bound, e.g. `T: Clone` or `<I as Iterator>::Item == u32`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BoundInfo {
+    predicate: String,
+    span: SpanInfo,
+}
+
+/// The generic context of an item with a body.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GenericsInfo {
+    #[serde(flatten)]
+    span: SpanInfo,
+    /// The item, as referred to by the `owner` of the records of its body.
+    owner: String,
+    /// The predicates of the item and its parents, with where they are written. Implicit
+    /// `Sized` bounds point at their parameter.
+    predicates: Vec<BoundInfo>,
+    /// The bounds the body is type checked with, i.e. the elaborated predicates, including the
+    /// supertraits of the bounds.
+    caller_bounds: Vec<String>,
+}
+
+impl GenericsInfo {
+    pub(crate) fn new(tcx: TyCtxt<'_>, def_id: DefId) -> Self {
+        let predicates = tcx.predicates_of(def_id).instantiate_identity(tcx);
+        GenericsInfo {
+            span: SpanInfo::new(tcx, tcx.def_span(def_id)),
+            owner: paths::def_path(tcx, def_id),
+            predicates: predicates
+                .predicates
+                .into_iter()
+                .zip(predicates.spans)
+                .map(|(predicate, span)| BoundInfo {
+                    predicate: paths::print(tcx, predicate),
+                    span: SpanInfo::new(tcx, span),
+                })
+                .collect(),
+            caller_bounds: tcx
+                .param_env(def_id)
+                .caller_bounds()
+                .iter()
+                .map(|clause| paths::print(tcx, clause))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! The memory layout of types: size, alignment, field offsets, niches and enum tags.
+
+use rustc_middle::ty::{self, CoroutineArgs, ParamEnv, Ty, TyCtxt};
+use rustc_target::abi::{FieldIdx, FieldsShape, LayoutS, TagEncoding, VariantIdx, Variants};
+use serde::{Deserialize, Serialize};
+
+/// A field and where it is stored.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FieldLayoutInfo {
+    /// The name of the field, or its index for tuples and closures.
+    name: String,
+    offset: u64,
+}
+
+/// The values a type leaves invalid, which enclosing enums can use to store their tag.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NicheInfo {
+    offset: u64,
+    /// The number of invalid values, saturated to `u64::MAX`.
+    available: u64,
+}
+
+/// How the variant of an enum or coroutine is encoded.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "encoding", rename_all = "snake_case")]
+pub enum TagInfo {
+    /// The variant index is stored in a tag field.
+    Direct { offset: u64, size: u64 },
+    /// The variant is encoded in invalid values of a field of `untagged_variant`, such as the
+    /// null pointer for `Option<&T>`.
+    Niche { offset: u64, size: u64, untagged_variant: String, niche_variants: Vec<String> },
+}
+
+/// The layout of a variant of an enum or coroutine.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VariantLayoutInfo {
+    name: String,
+    size: u64,
+    fields: Vec<FieldLayoutInfo>,
+}
+
+/// The layout of a type.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LayoutInfo {
+    size: u64,
+    align: u64,
+    uninhabited: bool,
+    /// The fields of structs, tuples, closures and enums with a single variant. Empty for
+    /// arrays.
+    fields: Vec<FieldLayoutInfo>,
+    /// The largest niche, if any.
+    niche: Option<NicheInfo>,
+    /// The tag of enums and coroutines with more than one variant.
+    tag: Option<TagInfo>,
+    variants: Vec<VariantLayoutInfo>,
+}
+
+fn variant_name(ty: Ty<'_>, variant: VariantIdx) -> String {
+    match ty.kind() {
+        ty::Adt(def, _) => def.variant(variant).name.to_string(),
+        ty::Coroutine(..) => CoroutineArgs::variant_name(variant).into_owned(),
+        _ => variant.as_usize().to_string(),
+    }
+}
+
+fn field_layouts(
+    ty: Ty<'_>,
+    variant: VariantIdx,
+    layout: &LayoutS<FieldIdx, VariantIdx>,
+) -> Vec<FieldLayoutInfo> {
+    // Arrays can be too long to list their elements.
+    if let FieldsShape::Array { .. } = layout.fields {
+        return Vec::new();
+    }
+    (0..layout.fields.count())
+        .map(|i| FieldLayoutInfo {
+            name: match ty.kind() {
+                ty::Adt(def, _) => {
+                    def.variant(variant).fields[FieldIdx::from_usize(i)].name.to_string()
+                }
+                _ => i.to_string(),
+            },
+            offset: layout.fields.offset(i).bytes(),
+        })
+        .collect()
+}
+
+impl LayoutInfo {
+    /// Returns `None` if the layout of `ty` cannot be computed, e.g. because it depends on
+    /// generic parameters.
+    pub(crate) fn new<'tcx>(
+        tcx: TyCtxt<'tcx>,
+        param_env: ParamEnv<'tcx>,
+        ty: Ty<'tcx>,
+    ) -> Option<Self> {
+        let layout = tcx.layout_of(param_env.and(ty)).ok()?;
+        let (fields, tag, variants) = match &layout.variants {
+            // The fields of an enum with a single variant are the ones of that variant.
+            Variants::Single { index } => (field_layouts(ty, *index, *layout), None, Vec::new()),
+            Variants::Multiple { tag, tag_encoding, tag_field, variants } => {
+                let offset = layout.fields.offset(*tag_field).bytes();
+                let size = tag.size(&tcx).bytes();
+                let tag = match tag_encoding {
+                    TagEncoding::Direct => TagInfo::Direct { offset, size },
+                    TagEncoding::Niche { untagged_variant, niche_variants, .. } => TagInfo::Niche {
+                        offset,
+                        size,
+                        untagged_variant: variant_name(ty, *untagged_variant),
+                        niche_variants: (niche_variants.start().as_usize()
+                            ..=niche_variants.end().as_usize())
+                            .map(|variant| variant_name(ty, VariantIdx::from_usize(variant)))
+                            .collect(),
+                    },
+                };
+                let variants = variants
+                    .iter_enumerated()
+                    .map(|(variant, variant_layout)| VariantLayoutInfo {
+                        name: variant_name(ty, variant),
+                        size: variant_layout.size.bytes(),
+                        fields: field_layouts(ty, variant, variant_layout),
+                    })
+                    .collect();
+                (Vec::new(), Some(tag), variants)
+            }
+        };
+        Some(LayoutInfo {
+            size: layout.size.bytes(),
+            align: layout.align.abi.bytes(),
+            uninhabited: layout.abi.is_uninhabited(),
+            fields,
+            niche: layout.largest_niche.map(|niche| NicheInfo {
+                offset: niche.offset.bytes(),
+                available: u64::try_from(niche.available(&tcx)).unwrap_or(u64::MAX),
+            }),
+            tag,
+            variants,
+        })
+    }
+}
@@ -0,0 +1,98 @@
+//! The source ranges where variables are live, and where their storage is live.
+
+use std::borrow::Cow;
+
+use rustc_index::interval::SparseIntervalMatrix;
+use rustc_middle::mir::{Body, Local, VarDebugInfoContents};
+use rustc_middle::ty::TyCtxt;
+use rustc_mir_dataflow::impls::{MaybeLiveLocals, MaybeStorageLive};
+use rustc_mir_dataflow::points::{save_as_intervals, DenseLocationMap, PointIndex};
+use rustc_mir_dataflow::storage::always_storage_live_locals;
+use rustc_mir_dataflow::Analysis;
+use rustc_span::Span;
+use serde::{Deserialize, Serialize};
+
+use super::borrowck::for_each_borrowck_body;
+use super::{paths, Record, SpanInfo};
+
+/// A variable and the source ranges of the code that runs while it is live.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LivenessInfo {
+    /// Where the variable is declared.
+    #[serde(flatten)]
+    span: SpanInfo,
+    /// The function or closure declaring the variable.
+    function: String,
+    variable: String,
+    ty: String,
+    /// Where the value of the variable may still be used later.
+    live: Vec<SpanInfo>,
+    /// Where the storage of the variable is allocated, from `StorageLive` to `StorageDead`.
+    storage_live: Vec<SpanInfo>,
+}
+
+/// Returns the source ranges covered by the points where `local` is in `matrix`, merging
+/// overlapping and adjacent ranges.
+fn ranges(
+    tcx: TyCtxt<'_>,
+    body: &Body<'_>,
+    points: &DenseLocationMap,
+    matrix: &SparseIntervalMatrix<Local, PointIndex>,
+    local: Local,
+) -> Vec<SpanInfo> {
+    let Some(row) = matrix.row(local) else { return Vec::new() };
+    // Code expanded from macros is attributed to the macro call.
+    let mut spans: Vec<Span> = row
+        .iter()
+        .map(|point| body.source_info(points.to_location(point)).span.source_callsite())
+        .filter(|span| !span.is_dummy())
+        .collect();
+    spans.sort_by_key(|span| (span.lo(), span.hi()));
+
+    let mut merged: Vec<Span> = Vec::new();
+    for span in spans {
+        match merged.last_mut() {
+            Some(last) if span.lo() <= last.hi() => {
+                if span.hi() > last.hi() {
+                    *last = last.with_hi(span.hi());
+                }
+            }
+            _ => merged.push(span),
+        }
+    }
+    merged.into_iter().map(|span| SpanInfo::new(tcx, span)).collect()
+}
+
+pub(crate) fn collect(tcx: TyCtxt<'_>) -> Vec<Record> {
+    let mut records = Vec::new();
+    for_each_borrowck_body(tcx, |def_id, body_with_facts| {
+        let body = &body_with_facts.body;
+        let points = DenseLocationMap::new(body);
+        let live = MaybeLiveLocals.into_engine(tcx, body).iterate_to_fixpoint();
+        let live = save_as_intervals(&points, body, live);
+        let always_live_locals = always_storage_live_locals(body);
+        let storage_live = MaybeStorageLive::new(Cow::Borrowed(&always_live_locals))
+            .into_engine(tcx, body)
+            .iterate_to_fixpoint();
+        let storage_live = save_as_intervals(&points, body, storage_live);
+
+        let function = paths::def_path(tcx, def_id);
+        for info in &body.var_debug_info {
+            // Captured variables of closures are not locals of the closure body.
+            let VarDebugInfoContents::Place(place) = info.value else { continue };
+            if info.composite.is_some() || !place.projection.is_empty() {
+                continue;
+            }
+            let liveness_info = LivenessInfo {
+                span: SpanInfo::new(tcx, info.source_info.span),
+                function: function.clone(),
+                variable: info.name.to_string(),
+                ty: paths::print(tcx, body.local_decls[place.local].ty),
+                live: ranges(tcx, body, &points, &live, place.local),
+                storage_live: ranges(tcx, body, &points, &storage_live, place.local),
+            };
+            records.push(Record::Liveness(liveness_info));
+        }
+    });
+    records
+}
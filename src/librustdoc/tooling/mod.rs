@@ -1,194 +1,251 @@
-use rustc_hir::Expr;
+use rustc_errors::ErrorGuaranteed;
+use rustc_hir::HirId;
 use rustc_middle::ty::TyCtxt;
-use std::path::PathBuf;
-use std::str;
-use serde::{Serialize, Deserialize};
+use rustc_span::def_id::LOCAL_CRATE;
+use rustc_span::{ExpnKind, MacroKind, Span};
+use serde::{Deserialize, Serialize};
 use serde_json;
-use std::fs::File;
-use std::io::Write;
-use serde_json::Value;
-
-
-
-#[allow(unused_variables)]
+use std::path::{Path, PathBuf};
+
+use crate::MainResult;
+
+mod borrowck;
+mod borrows;
+mod callgraph;
+mod calls;
+mod closures;
+mod config;
+mod coroutines;
+mod drops;
+mod exprs;
+mod generics;
+mod layouts;
+mod liveness;
+mod mono;
+mod output;
+mod panics;
+mod paths;
+mod places;
+mod traits;
+mod types;
+mod unsafety;
+mod vars;
+
+pub(crate) use borrowck::{override_queries, BorrowckBodies};
+use borrows::EventInfo;
+use callgraph::{CallEdgeInfo, CallGraphView, CallNodeInfo};
+use calls::CallInfo;
+use closures::ClosureInfo;
+use config::ToolingAnalysis;
+pub(crate) use config::ToolingOptions;
+use coroutines::CoroutineInfo;
+use drops::DropInfo;
+use exprs::ExprInfo;
+use generics::GenericsInfo;
+use liveness::LivenessInfo;
+use mono::InstanceInfo;
+use panics::PanicInfo;
+use unsafety::UnsafeInfo;
+use vars::{AssignInfo, UseInfo, VarInfo};
+
+/// The source range covered by a record.
 #[derive(Serialize, Deserialize, Debug)]
-pub struct VarInfo {
-    name: String,
+pub struct SpanInfo {
     start_line: usize,
     start_col: usize,
     start_file: Option<PathBuf>,
     end_line: usize,
     end_col: usize,
     end_file: Option<PathBuf>,
-    ty: Option<String>,
+    /// Whether the range comes from a macro expansion or a compiler desugaring.
+    from_expansion: bool,
+    /// The outermost macro the range was expanded from, if any.
+    expansion: Option<ExpansionInfo>,
 }
 
-pub fn extract_local_path(name: &rustc_span::FileName) -> Option<PathBuf> {
-    if let rustc_span::FileName::Real(f) = name {
-        if let rustc_span::RealFileName::LocalPath(p) = f {
-            Some(p.clone())
-        } else {
-            None
+impl SpanInfo {
+    fn new(tcx: TyCtxt<'_>, span: Span) -> Self {
+        let source_map = tcx.sess.source_map();
+        let data = span.data();
+        let start = source_map.lookup_char_pos(data.lo);
+        let end = source_map.lookup_char_pos(data.hi);
+        SpanInfo {
+            start_line: start.line,
+            start_col: start.col_display,
+            start_file: extract_local_path(&start.file.name),
+            end_line: end.line,
+            end_col: end.col_display,
+            end_file: extract_local_path(&end.file.name),
+            from_expansion: span.from_expansion(),
+            expansion: ExpansionInfo::new(tcx, span),
         }
-    } else {
-        None
     }
 }
 
-struct HirVisitor<'tcx> {
-    tcx: TyCtxt<'tcx>,
-    info: Vec<String>,
+/// The kind of macro an expansion comes from.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum MacroKindInfo {
+    Bang,
+    Attr,
+    Derive,
 }
 
-struct All;
-
-impl<'hir> rustc_hir::intravisit::nested_filter::NestedFilter<'hir> for All {
-    type Map = rustc_middle::hir::map::Map<'hir>;
-    const INTER: bool = false;
-    const INTRA: bool = true;
+impl From<MacroKind> for MacroKindInfo {
+    fn from(kind: MacroKind) -> Self {
+        match kind {
+            MacroKind::Bang => MacroKindInfo::Bang,
+            MacroKind::Attr => MacroKindInfo::Attr,
+            MacroKind::Derive => MacroKindInfo::Derive,
+        }
+    }
 }
 
-impl<'tcx> rustc_hir::intravisit::Visitor<'tcx> for HirVisitor<'tcx> {
-    type Map = rustc_middle::hir::map::Map<'tcx>;
-    //type NestedFilter = All;
-    type NestedFilter = rustc_middle::hir::nested_filter::OnlyBodies;
+/// The macro invocation a source range was expanded from.
+#[derive(Serialize, Deserialize, Debug)]
+struct ExpansionInfo {
+    macro_name: String,
+    macro_kind: MacroKindInfo,
+    /// The range of the invocation in the code that was not produced by any expansion.
+    call_site: Box<SpanInfo>,
+}
 
-    fn nested_visit_map(&mut self) -> Self::Map {
-        self.tcx.hir()
+impl ExpansionInfo {
+    /// Returns the outermost macro `span` was expanded from. Desugarings and other
+    /// expansions done by the compiler are skipped, as they have no invocation to point to.
+    fn new(tcx: TyCtxt<'_>, span: Span) -> Option<Self> {
+        let (macro_kind, name) = span
+            .macro_backtrace()
+            .filter_map(|expn_data| match expn_data.kind {
+                ExpnKind::Macro(kind, name) => Some((kind, name)),
+                _ => None,
+            })
+            .last()?;
+        Some(ExpansionInfo {
+            macro_name: name.to_string(),
+            macro_kind: macro_kind.into(),
+            call_site: Box::new(SpanInfo::new(tcx, span.source_callsite())),
+        })
     }
+}
 
-    // Represents a `let` statement (i.e., `let <pat>:<ty> = <init>;`).
-    fn visit_local(&mut self, local: &'tcx rustc_hir::Local<'tcx>) {
-        let source_map = self.tcx.sess.source_map();
-        if let Some(ident) = local.pat.simple_ident() {
-            let ident_name = ident.name.as_str().to_string();
-
-            // println!("{:#?}", var_span);
-            let var_span = local.pat.span.data();
-            let start = source_map.lookup_char_pos(var_span.lo);
-            let end = source_map.lookup_char_pos(var_span.hi);
-
-            let start_path = extract_local_path(&start.file.name);
-            let end_path = extract_local_path(&end.file.name);
-
-            let ty = if let Some(expr) = local.init {
-                let hir_id = expr.hir_id;
-                let def_id = hir_id.owner.def_id;
-                let ty = self.tcx.typeck(def_id).node_type(hir_id);
-                Some(ty.to_string())
-            } else {
-                None
-            };
-
-            let var_info = VarInfo {
-                name: ident_name,
-                start_line: start.line,
-                start_col: start.col_display,
-                end_line: end.line,
-                end_col: end.col_display,
-                ty,
-                start_file: start_path,
-                end_file: end_path,
-            };
-            let var_info_json = serde_json::to_string(&var_info).unwrap();
-            self.info.push(var_info_json);
-            //println!("{:#?}", var_info_json);
-        }
+/// A record of the tooling output, tagged with its kind.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum Record {
+    Var(VarInfo),
+    Use(UseInfo),
+    Assign(AssignInfo),
+    Expr(ExprInfo),
+    Call(CallInfo),
+    CallNode(CallNodeInfo),
+    CallEdge(CallEdgeInfo),
+    Closure(ClosureInfo),
+    Event(EventInfo),
+    Liveness(LivenessInfo),
+    Drop(DropInfo),
+    Unsafe(UnsafeInfo),
+    Panic(PanicInfo),
+    Coroutine(CoroutineInfo),
+    Generics(GenericsInfo),
+    Instance(InstanceInfo),
+}
 
-        
+/// Returns an identifier for the binding `hir_id`, made of the `DefPathHash` of its owner
+/// and its `ItemLocalId`. Unlike the name, it tells shadowed bindings apart, and unlike the
+/// `DefIndex` it stays the same across compilation sessions.
+pub(crate) fn binding_id(tcx: TyCtxt<'_>, hir_id: HirId) -> String {
+    let owner_hash = tcx.def_path_hash(hir_id.owner.to_def_id());
+    format!("{:032x}:{}", u128::from_le_bytes(owner_hash.0.to_le_bytes()), hir_id.local_id.as_u32())
+}
 
-        rustc_hir::intravisit::walk_local(self, local);
+pub fn extract_local_path(name: &rustc_span::FileName) -> Option<PathBuf> {
+    if let rustc_span::FileName::Real(f) = name {
+        if let rustc_span::RealFileName::LocalPath(p) = f {
+            Some(p.clone())
+        } else {
+            None
+        }
+    } else {
+        None
     }
+}
 
-    // AssignExpr: An assignment (e.g., `a = foo()`).
-    // We only consider the right hand of the assignment expr
-    fn visit_expr(&mut self, ex: &'tcx Expr<'tcx>) {
-        let source_map = self.tcx.sess.source_map();
-
-        if let rustc_hir::ExprKind::Assign(rh_ex, _, _) = ex.kind {
-            let hir_id = rh_ex.hir_id;
-            let def_id = hir_id.owner.def_id;
-            let ty = self.tcx.typeck(def_id).node_type(hir_id).to_string();
-            if let rustc_hir::ExprKind::Path(qpath) = rh_ex.kind {
-                if let rustc_hir::QPath::Resolved(_, p) = qpath {
-                    if let Some(seg) = p.segments.last() {
-                        //TODO: Didn't consider the more than 1 pathseg: `mod_a::StructB`
-                        let var_name = seg.ident.name.as_str().to_string();
-                        let var_span = p.span.data();
-
-                        let start = source_map.lookup_char_pos(var_span.lo);
-                        let end = source_map.lookup_char_pos(var_span.hi);
-                        let start_path = extract_local_path(&start.file.name);
-                        let end_path = extract_local_path(&end.file.name);
-
-                        let var_info = VarInfo {
-                            name: var_name,
-                            start_line: start.line,
-                            start_col: start.col_display,
-                            end_line: end.line,
-                            end_col: end.col_display,
-                            ty: Some(ty),
-                            start_file: start_path,
-                            end_file: end_path,
-                        };
-                        let var_info_json = serde_json::to_string(&var_info).unwrap();
-                        self.info.push(var_info_json);
-                        //println!("{:#?}", var_info_json);
-                    }
+pub(crate) fn get_infos(tcx: TyCtxt<'_>, options: &ToolingOptions) -> MainResult {
+    let crate_name = tcx.crate_name(LOCAL_CRATE);
+    let crate_file_stem = config::crate_file_stem(
+        crate_name.as_str(),
+        // A crate built with several crate types is named after the first one.
+        tcx.crate_types()[0],
+        tcx.stable_crate_id(LOCAL_CRATE).as_u64(),
+        tcx.sess.opts.target_triple.triple(),
+    );
+
+    for &analysis in &options.analyses {
+        // Call graphs are also rendered with Graphviz.
+        let mut dot = None;
+        let records = tcx.sess.time(analysis.name(), || {
+            paths::with_path_style(options.path_style, || match analysis {
+                ToolingAnalysis::Vars => vars::collect(tcx, options),
+                ToolingAnalysis::Exprs => exprs::collect(tcx),
+                ToolingAnalysis::Calls => calls::collect(tcx),
+                ToolingAnalysis::CallGraph | ToolingAnalysis::MonoCallGraph => {
+                    let view = if analysis == ToolingAnalysis::CallGraph {
+                        CallGraphView::Generic
+                    } else {
+                        CallGraphView::Instance
+                    };
+                    let graph = callgraph::build(tcx, view);
+                    dot = Some(graph.to_dot());
+                    graph.into_records()
                 }
+                ToolingAnalysis::Closures => closures::collect(tcx),
+                ToolingAnalysis::Borrows => borrows::collect(tcx),
+                ToolingAnalysis::Liveness => liveness::collect(tcx),
+                ToolingAnalysis::Drops => drops::collect(tcx),
+                ToolingAnalysis::Unsafe => unsafety::collect(tcx),
+                ToolingAnalysis::Panics => panics::collect(tcx),
+                ToolingAnalysis::Coroutines => coroutines::collect(tcx),
+                ToolingAnalysis::MonoLocals => mono::collect(tcx),
+            })
+        });
+        let (kept, records): (Vec<_>, Vec<_>) = records
+            .iter()
+            .map(|record| (record, serde_json::to_value(record).unwrap()))
+            .filter(|(_, record)| !(options.skip_expansions && is_from_macro(record)))
+            .unzip();
+        let columns = output::columns(kept);
+
+        let path = options.output_path(&crate_file_stem, analysis, options.format.extension());
+        if let Err(e) = output::write_records(&records, &columns, options.format, &path) {
+            return Err(write_error(tcx, &path, e));
+        }
+        info!("tooling results have been saved to {}", path.display());
+
+        if let Some(dot) = dot {
+            let path = options.output_path(&crate_file_stem, analysis, "dot");
+            if let Err(e) = std::fs::write(&path, dot) {
+                return Err(write_error(tcx, &path, e));
             }
+            info!("tooling results have been saved to {}", path.display());
         }
-        rustc_hir::intravisit::walk_expr(self, ex);
     }
-
-
-}
-
-
-pub fn save_json_array_to_file(json_strings: Vec<String>, file_path: &str) -> std::io::Result<()> {
-    // 将所有的JSON字符串转换为serde_json::Value，并收集到一个Vec中
-    let json_values: Vec<Value> = json_strings
-        .into_iter()
-        .filter_map(|s| serde_json::from_str(&s).ok())
-        .collect();
-
-    // 将Vec<Value>转换为一个JSON数组字符串
-    let json_array = serde_json::to_string(&json_values).unwrap_or_else(|_| "[]".to_string());
-
-    // 创建并写入文件
-    let mut file = File::create(file_path)?;
-    file.write_all(json_array.as_bytes())?;
-
     Ok(())
 }
 
-pub fn get_infos(tcx: TyCtxt<'_>) {
-    //let hir_krate = tcx.hir();
-    //let source_map = tcx.sess.source_map();
-    //parse(tcx);
-
-    let mut visitor = HirVisitor { 
-        tcx,
-        info: Vec::new(),
-    };
-
-    tcx.hir().visit_all_item_likes_in_crate(&mut visitor);
-
-    // for id in hir_krate.items() {
-    //     rustc_hir::intravisit::Visitor::visit_nested_item(&mut visitor, id);
-    // }
-
-    //println!("{:#?}", visitor.info);
+/// Whether the record is located in code produced by a macro expansion, rather than in code
+/// written at the call site. The span fields are flattened into the records, so `expansion`
+/// is one of their top-level keys.
+fn is_from_macro(record: &serde_json::Value) -> bool {
+    record.get("expansion").is_some_and(|expansion| !expansion.is_null())
+}
 
-    // save to JSON file
-    let file_path = "output.json";
-    if let Err(e) = save_json_array_to_file(visitor.info, file_path) {
-        println!("An error occurred: {}", e);
-    } else {
-        println!("JSON array has been saved to {} successfully.", file_path);
-    }
+fn write_error(tcx: TyCtxt<'_>, path: &Path, e: std::io::Error) -> ErrorGuaranteed {
+    tcx.dcx().err(format!("couldn't write tooling results to \"{}\": {e}", path.display()))
 }
 
+#[cfg(test)]
+mod tests;
 
 // pub fn get_type_info(tcx: TyCtxt<'_>) {
 //     let hir_krate = tcx.hir();
@@ -206,4 +263,4 @@ pub fn get_infos(tcx: TyCtxt<'_>) {
 //             }
 //         }
 //     }
-// }
\ No newline at end of file
+// }
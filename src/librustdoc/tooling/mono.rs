@@ -0,0 +1,105 @@
+//! The concrete types of the locals of every monomorphized instance of the local functions.
+
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_middle::mir::mono::MonoItem;
+use rustc_middle::mir::{Local, VarDebugInfoContents};
+use rustc_middle::ty::{self, Instance, InstanceDef, TyCtxt};
+use serde::{Deserialize, Serialize};
+
+use super::calls::generic_args_to_strings;
+use super::{paths, Record, SpanInfo};
+
+/// A local of a monomorphized instance.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MonoLocalInfo {
+    /// The MIR local, e.g. `_3`.
+    local: String,
+    /// The name of the variable, or `None` for temporaries.
+    name: Option<String>,
+    span: SpanInfo,
+    ty: String,
+    /// The size in bytes, if the layout of the type can be computed.
+    size: Option<u64>,
+}
+
+/// A monomorphized instance of a local function or closure, as collected for code generation.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InstanceInfo {
+    #[serde(flatten)]
+    span: SpanInfo,
+    /// The generic function or closure.
+    function: String,
+    /// The instance, e.g. `parse::<u32>`.
+    instance: String,
+    generic_args: Vec<String>,
+    /// The locals of the instance, starting with the return place and the arguments.
+    locals: Vec<MonoLocalInfo>,
+}
+
+fn collect_instance<'tcx>(tcx: TyCtxt<'tcx>, instance: Instance<'tcx>) -> InstanceInfo {
+    let param_env = ty::ParamEnv::reveal_all();
+    let body = tcx.instance_mir(instance.def);
+    let names: FxHashMap<Local, _> = body
+        .var_debug_info
+        .iter()
+        .filter_map(|info| match info.value {
+            VarDebugInfoContents::Place(place) if place.projection.is_empty() => {
+                Some((place.local, info.name))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let locals = body
+        .local_decls
+        .iter_enumerated()
+        .map(|(local, decl)| {
+            let ty = instance.instantiate_mir_and_normalize_erasing_regions(
+                tcx,
+                param_env,
+                ty::EarlyBinder::bind(decl.ty),
+            );
+            MonoLocalInfo {
+                local: format!("{local:?}"),
+                name: names.get(&local).map(|name| name.to_string()),
+                span: SpanInfo::new(tcx, decl.source_info.span),
+                ty: paths::print(tcx, ty),
+                size: tcx.layout_of(param_env.and(ty)).ok().map(|layout| layout.size.bytes()),
+            }
+        })
+        .collect();
+
+    InstanceInfo {
+        span: SpanInfo::new(tcx, tcx.def_span(instance.def_id())),
+        function: paths::def_path(tcx, instance.def_id()),
+        instance: paths::instance_path(tcx, instance),
+        generic_args: generic_args_to_strings(tcx, instance.args),
+        locals,
+    }
+}
+
+/// Collects the instances of local functions and closures that code generation would emit.
+///
+/// The mono items are the ones found by `rustc_monomorphize::collector`, through the query
+/// that partitions them into codegen units.
+pub(crate) fn collect(tcx: TyCtxt<'_>) -> Vec<Record> {
+    let (_, codegen_units) = tcx.collect_and_partition_mono_items(());
+    // Inline items are copied into every codegen unit that uses them.
+    let instances: FxHashSet<_> = codegen_units
+        .iter()
+        .flat_map(|cgu| cgu.items().keys())
+        .filter_map(|item| match *item {
+            MonoItem::Fn(instance @ Instance { def: InstanceDef::Item(def_id), .. })
+                if def_id.is_local() =>
+            {
+                Some(instance)
+            }
+            _ => None,
+        })
+        .collect();
+    let mut instances: Vec<_> =
+        instances.into_iter().map(|instance| collect_instance(tcx, instance)).collect();
+    // The order of the items of a codegen unit is not stable.
+    instances.sort_by(|a, b| a.instance.cmp(&b.instance));
+    instances.into_iter().map(Record::Instance).collect()
+}
@@ -0,0 +1,260 @@
+//! Serialization of tooling records into the supported result formats.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use serde::ser::{self, Impossible, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use super::config::ToolingFormat;
+
+/// Writes `records` to `path` in the given format. `columns` are the CSV columns, see
+/// [`columns`].
+pub(crate) fn write_records(
+    records: &[Value],
+    columns: &[String],
+    format: ToolingFormat,
+    path: &Path,
+) -> io::Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut out = BufWriter::new(File::create(path)?);
+    match format {
+        ToolingFormat::Json => serde_json::to_writer(&mut out, records)?,
+        ToolingFormat::Jsonl => {
+            for record in records {
+                serde_json::to_writer(&mut out, record)?;
+                out.write_all(b"\n")?;
+            }
+        }
+        ToolingFormat::Csv => write_csv(&mut out, records, columns)?,
+    }
+    out.flush()
+}
+
+/// Returns the union of the fields of `records`, in the order they are first seen.
+///
+/// `serde_json::Map` sorts its keys, so the columns are taken from the records themselves to
+/// keep the fields in the order they are declared in.
+pub(crate) fn columns<'a, T: Serialize + 'a>(
+    records: impl IntoIterator<Item = &'a T>,
+) -> Vec<String> {
+    let mut columns = FieldNames(Vec::new());
+    for record in records {
+        // Records that are not objects have no fields to add.
+        let _ = record.serialize(&mut columns);
+    }
+    columns.0
+}
+
+/// Writes `records` as CSV, with one column per entry of `columns`. Nested values are written
+/// as JSON text.
+fn write_csv(out: &mut impl Write, records: &[Value], columns: &[String]) -> io::Result<()> {
+    let empty = Map::new();
+    write_csv_row(out, columns.iter().cloned())?;
+    for record in records {
+        let fields = record.as_object().unwrap_or(&empty);
+        write_csv_row(out, columns.iter().map(|c| csv_cell(fields.get(c))))?;
+    }
+    Ok(())
+}
+
+fn write_csv_row(out: &mut impl Write, cells: impl Iterator<Item = String>) -> io::Result<()> {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            out.write_all(b",")?;
+        }
+        if cell.contains([',', '"', '\n', '\r']) {
+            write!(out, "\"{}\"", cell.replace('"', "\"\""))?;
+        } else {
+            out.write_all(cell.as_bytes())?;
+        }
+    }
+    out.write_all(b"\n")
+}
+
+fn csv_cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+/// A serializer collecting the names of the fields of a struct or map, including flattened and
+/// tag fields, without serializing their values.
+struct FieldNames(Vec<String>);
+
+impl FieldNames {
+    fn add(&mut self, name: &str) {
+        if !self.0.iter().any(|field| field == name) {
+            self.0.push(name.to_string());
+        }
+    }
+}
+
+fn not_an_object() -> serde_json::Error {
+    ser::Error::custom("a tooling record must be serialized as an object")
+}
+
+/// Implements the methods of `Serializer` for values that are not objects.
+macro_rules! not_an_object {
+    ($($method:ident($($ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<(), serde_json::Error> {
+                Err(not_an_object())
+            }
+        )*
+    };
+}
+
+impl<'a> Serializer for &'a mut FieldNames {
+    type Ok = ();
+    type Error = serde_json::Error;
+    type SerializeSeq = Impossible<(), serde_json::Error>;
+    type SerializeTuple = Impossible<(), serde_json::Error>;
+    type SerializeTupleStruct = Impossible<(), serde_json::Error>;
+    type SerializeTupleVariant = Impossible<(), serde_json::Error>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), serde_json::Error>;
+
+    not_an_object! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+        serialize_unit_variant(&'static str, u32, &'static str),
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), Self::Error> {
+        Err(not_an_object())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(not_an_object())
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(not_an_object())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(not_an_object())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(not_an_object())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(not_an_object())
+    }
+}
+
+impl<'a> ser::SerializeMap for &'a mut FieldNames {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        match serde_json::to_value(key)? {
+            Value::String(name) => {
+                self.add(&name);
+                Ok(())
+            }
+            _ => Err(ser::Error::custom("the keys of a tooling record must be strings")),
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, _: &T) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStruct for &'a mut FieldNames {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        name: &'static str,
+        _: &T,
+    ) -> Result<(), Self::Error> {
+        self.add(name);
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,59 @@
+use serde::Serialize;
+use serde_json::json;
+
+use super::{columns, write_csv};
+
+fn csv(records: &[serde_json::Value], columns: &[&str]) -> String {
+    let columns: Vec<_> = columns.iter().map(|column| column.to_string()).collect();
+    let mut out = Vec::new();
+    write_csv(&mut out, records, &columns).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn csv_leaves_missing_fields_empty() {
+    let records = [json!({"name": "a", "line": 1}), json!({"name": "b", "ty": "u8"})];
+    assert_eq!(csv(&records, &["name", "line", "ty"]), "name,line,ty\na,1,\nb,,u8\n");
+}
+
+#[test]
+fn csv_quotes_special_characters() {
+    let records = [json!({"ty": "(u8, u16)", "name": "say \"hi\""})];
+    assert_eq!(csv(&records, &["ty", "name"]), "ty,name\n\"(u8, u16)\",\"say \"\"hi\"\"\"\n");
+}
+
+#[test]
+fn csv_writes_nested_values_as_json() {
+    let records = [json!({"args": ["u8", null]})];
+    assert_eq!(csv(&records, &["args"]), "args\n\"[\"\"u8\"\",null]\"\n");
+}
+
+#[derive(Serialize)]
+struct Span {
+    line: usize,
+    col: usize,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum Record {
+    Var {
+        name: String,
+        #[serde(flatten)]
+        span: Span,
+        ty: Option<String>,
+    },
+    Use {
+        name: String,
+        binding: String,
+    },
+}
+
+#[test]
+fn columns_follow_field_declarations() {
+    let records = [
+        Record::Var { name: "x".into(), span: Span { line: 1, col: 4 }, ty: None },
+        Record::Use { name: "x".into(), binding: "0:1".into() },
+    ];
+    assert_eq!(columns(&records), ["record", "name", "line", "col", "ty", "binding"]);
+}
@@ -0,0 +1,147 @@
+//! Places where a function can panic, read from optimized MIR.
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::{AssertKind, Body, TerminatorKind};
+use rustc_middle::ty::{self, TyCtxt};
+use rustc_span::hygiene::{ExpnKind, MacroKind};
+use rustc_span::{sym, Span};
+use serde::{Deserialize, Serialize};
+
+use super::{paths, Record, SpanInfo};
+
+/// Why code may panic.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PanicKind {
+    /// A call to a panic entry point, usually from `panic!`, `unreachable!`, `todo!`,
+    /// `unimplemented!` or a failed `assert!`.
+    Explicit,
+    /// `Option::unwrap`, `Result::unwrap` and `Result::unwrap_err`.
+    Unwrap,
+    /// `Option::expect`, `Result::expect` and `Result::expect_err`.
+    Expect,
+    /// Indexing a slice or an array out of bounds.
+    BoundsCheck,
+    /// Arithmetic overflow, when overflow checks are enabled.
+    Overflow,
+    DivisionByZero,
+    RemainderByZero,
+    /// `RefCell::borrow` or `RefCell::borrow_mut` while the cell is already borrowed.
+    RefCellBorrow,
+    /// A call to `Index::index` or `IndexMut::index_mut`, which panic out of bounds for the
+    /// standard collections.
+    OverloadedIndex,
+    /// Dereferencing a misaligned pointer, when debug assertions are enabled.
+    MisalignedPointer,
+    /// Resuming a coroutine after it completed or panicked.
+    CoroutineResumed,
+}
+
+/// A place that can panic.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PanicInfo {
+    #[serde(flatten)]
+    span: SpanInfo,
+    /// The function or closure containing the panic site.
+    function: String,
+    kind: PanicKind,
+    /// The called function, for panics from calls.
+    callee: Option<String>,
+    /// The outermost macro the panic site comes from, e.g. `todo` or `assert_eq`.
+    macro_name: Option<String>,
+}
+
+/// Returns the outermost bang macro `span` was expanded from.
+fn outermost_macro(span: Span) -> Option<String> {
+    span.macro_backtrace()
+        .filter_map(|expn_data| match expn_data.kind {
+            ExpnKind::Macro(MacroKind::Bang, name) => Some(name.to_string()),
+            _ => None,
+        })
+        .last()
+}
+
+/// Classifies a call of `callee` as a panic site.
+fn classify_call(tcx: TyCtxt<'_>, callee: DefId) -> Option<PanicKind> {
+    let name = tcx.opt_item_name(callee)?;
+    let crate_name = tcx.crate_name(callee.krate);
+    if (crate_name == sym::core || crate_name == sym::std)
+        && (tcx.opt_parent(callee).and_then(|parent| tcx.opt_item_name(parent))
+            == Some(sym::panicking)
+            || name.as_str() == "begin_panic")
+    {
+        return Some(PanicKind::Explicit);
+    }
+
+    if let Some(trait_def_id) = tcx.trait_of_item(callee) {
+        let lang_items = tcx.lang_items();
+        return (Some(trait_def_id) == lang_items.index_trait()
+            || Some(trait_def_id) == lang_items.index_mut_trait())
+        .then_some(PanicKind::OverloadedIndex);
+    }
+
+    let impl_def_id = tcx.impl_of_method(callee)?;
+    let ty::Adt(adt_def, _) = tcx.type_of(impl_def_id).instantiate_identity().kind() else {
+        return None;
+    };
+    let adt = adt_def.did();
+    if tcx.is_diagnostic_item(sym::Option, adt) || tcx.is_diagnostic_item(sym::Result, adt) {
+        match name.as_str() {
+            "unwrap" | "unwrap_err" => Some(PanicKind::Unwrap),
+            "expect" | "expect_err" => Some(PanicKind::Expect),
+            _ => None,
+        }
+    } else if tcx.is_diagnostic_item(sym::RefCell, adt) {
+        matches!(name.as_str(), "borrow" | "borrow_mut").then_some(PanicKind::RefCellBorrow)
+    } else {
+        None
+    }
+}
+
+fn collect_body<'tcx>(tcx: TyCtxt<'tcx>, body: &Body<'tcx>, records: &mut Vec<Record>) {
+    let function = paths::def_path(tcx, body.source.def_id());
+    for data in body.basic_blocks.iter() {
+        let terminator = data.terminator();
+        let (kind, callee) = match &terminator.kind {
+            TerminatorKind::Assert { msg, .. } => {
+                let kind = match **msg {
+                    AssertKind::BoundsCheck { .. } => PanicKind::BoundsCheck,
+                    AssertKind::Overflow(..) | AssertKind::OverflowNeg(_) => PanicKind::Overflow,
+                    AssertKind::DivisionByZero(_) => PanicKind::DivisionByZero,
+                    AssertKind::RemainderByZero(_) => PanicKind::RemainderByZero,
+                    AssertKind::ResumedAfterReturn(_) | AssertKind::ResumedAfterPanic(_) => {
+                        PanicKind::CoroutineResumed
+                    }
+                    AssertKind::MisalignedPointerDereference { .. } => PanicKind::MisalignedPointer,
+                };
+                (kind, None)
+            }
+            TerminatorKind::Call { func, .. } => {
+                let ty::FnDef(callee, _) = *func.ty(body, tcx).kind() else { continue };
+                let Some(kind) = classify_call(tcx, callee) else { continue };
+                (kind, Some(callee))
+            }
+            _ => continue,
+        };
+        let span = terminator.source_info.span;
+        let panic_info = PanicInfo {
+            span: SpanInfo::new(tcx, span.source_callsite()),
+            function: function.clone(),
+            kind,
+            callee: callee.map(|def_id| paths::def_path(tcx, def_id)),
+            macro_name: outermost_macro(span),
+        };
+        records.push(Record::Panic(panic_info));
+    }
+}
+
+pub(crate) fn collect(tcx: TyCtxt<'_>) -> Vec<Record> {
+    let mut records = Vec::new();
+    for def_id in tcx.hir().body_owners() {
+        if matches!(tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn | DefKind::Closure) {
+            collect_body(tcx, tcx.optimized_mir(def_id), &mut records);
+        }
+    }
+    records
+}
@@ -0,0 +1,183 @@
+//! Printing of types and item paths in the style chosen with `--tooling-path-style`.
+//!
+//! How rustc prints paths depends on flags that are scattered over the compiler, so the same
+//! type could otherwise be printed as `Vec<String>` in one record and as
+//! `std::vec::Vec<std::string::String>` in another. Every type and path the analyses emit goes
+//! through this module instead.
+
+use std::cell::Cell;
+use std::fmt::{self, Write};
+
+use rustc_hir::def_id::{CrateNum, DefId};
+use rustc_hir::definitions::DisambiguatedDefPathData;
+use rustc_middle::ty::print::{
+    with_crate_prefix, with_forced_trimmed_paths, with_no_trimmed_paths, PrettyPrinter, Print,
+    PrintError, Printer,
+};
+use rustc_middle::ty::{self, GenericArg, GenericArgKind, Instance, Ty, TyCtxt};
+
+use super::config::PathStyle;
+
+thread_local! {
+    static PATH_STYLE: Cell<PathStyle> = const { Cell::new(PathStyle::FullyQualified) };
+}
+
+/// Runs `f`, printing the types and paths of the records it creates in the given style.
+pub(crate) fn with_path_style<R>(style: PathStyle, f: impl FnOnce() -> R) -> R {
+    let old = PATH_STYLE.replace(style);
+    let result = f();
+    PATH_STYLE.set(old);
+    result
+}
+
+fn print_with<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    display: impl FnOnce() -> String,
+    canonical: impl FnOnce(&mut CanonicalPrinter<'tcx>) -> Result<(), PrintError>,
+) -> String {
+    match PATH_STYLE.get() {
+        PathStyle::Trimmed => with_forced_trimmed_paths!(display()),
+        PathStyle::CrateRelative => with_crate_prefix!(with_no_trimmed_paths!(display())),
+        PathStyle::FullyQualified => with_no_trimmed_paths!(display()),
+        PathStyle::Canonical => {
+            let mut printer = CanonicalPrinter { tcx, out: String::new() };
+            canonical(&mut printer).unwrap();
+            printer.out
+        }
+    }
+}
+
+/// Prints a type, a generic argument, a const or a predicate.
+pub(crate) fn print<'tcx, T>(tcx: TyCtxt<'tcx>, value: T) -> String
+where
+    T: fmt::Display + Print<'tcx, CanonicalPrinter<'tcx>>,
+{
+    print_with(tcx, || value.to_string(), |printer| value.print(printer))
+}
+
+/// Prints the path of an item.
+pub(crate) fn def_path(tcx: TyCtxt<'_>, def_id: DefId) -> String {
+    print_with(tcx, || tcx.def_path_str(def_id), |printer| printer.print_def_path(def_id, &[]))
+}
+
+/// Prints the path of an instance, followed by its generic arguments.
+pub(crate) fn instance_path<'tcx>(tcx: TyCtxt<'tcx>, instance: Instance<'tcx>) -> String {
+    print_with(
+        tcx,
+        || instance.to_string(),
+        |printer| printer.print_def_path(instance.def_id(), instance.args),
+    )
+}
+
+/// Prints paths as the `DefPath` of items, starting with the name and `StableCrateId` of their
+/// crate. Unlike the other styles, these paths don't depend on which re-exports are visible
+/// from the analyzed crate.
+pub(crate) struct CanonicalPrinter<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    out: String,
+}
+
+impl<'tcx> Printer<'tcx> for CanonicalPrinter<'tcx> {
+    fn tcx(&self) -> TyCtxt<'tcx> {
+        self.tcx
+    }
+
+    fn print_region(&mut self, region: ty::Region<'tcx>) -> Result<(), PrintError> {
+        if let Some(name) = region.get_name() {
+            write!(self, "{name}")?;
+        }
+        Ok(())
+    }
+
+    fn print_type(&mut self, ty: Ty<'tcx>) -> Result<(), PrintError> {
+        self.pretty_print_type(ty)
+    }
+
+    fn print_dyn_existential(
+        &mut self,
+        predicates: &'tcx ty::List<ty::PolyExistentialPredicate<'tcx>>,
+    ) -> Result<(), PrintError> {
+        self.pretty_print_dyn_existential(predicates)
+    }
+
+    fn print_const(&mut self, ct: ty::Const<'tcx>) -> Result<(), PrintError> {
+        self.pretty_print_const(ct, false)
+    }
+
+    fn path_crate(&mut self, cnum: CrateNum) -> Result<(), PrintError> {
+        let stable_crate_id = self.tcx.stable_crate_id(cnum);
+        write!(self, "{}[{:016x}]", self.tcx.crate_name(cnum), stable_crate_id.as_u64())
+    }
+
+    fn path_qualified(
+        &mut self,
+        self_ty: Ty<'tcx>,
+        trait_ref: Option<ty::TraitRef<'tcx>>,
+    ) -> Result<(), PrintError> {
+        self.pretty_path_qualified(self_ty, trait_ref)
+    }
+
+    fn path_append_impl(
+        &mut self,
+        print_prefix: impl FnOnce(&mut Self) -> Result<(), PrintError>,
+        _disambiguated_data: &DisambiguatedDefPathData,
+        self_ty: Ty<'tcx>,
+        trait_ref: Option<ty::TraitRef<'tcx>>,
+    ) -> Result<(), PrintError> {
+        self.pretty_path_append_impl(
+            |cx| {
+                print_prefix(cx)?;
+                cx.write_str("::")
+            },
+            self_ty,
+            trait_ref,
+        )
+    }
+
+    fn path_append(
+        &mut self,
+        print_prefix: impl FnOnce(&mut Self) -> Result<(), PrintError>,
+        disambiguated_data: &DisambiguatedDefPathData,
+    ) -> Result<(), PrintError> {
+        print_prefix(self)?;
+        // Includes the disambiguator, e.g. `{closure#1}`.
+        write!(self, "::{disambiguated_data}")
+    }
+
+    fn path_generic_args(
+        &mut self,
+        print_prefix: impl FnOnce(&mut Self) -> Result<(), PrintError>,
+        args: &[GenericArg<'tcx>],
+    ) -> Result<(), PrintError> {
+        print_prefix(self)?;
+        let args =
+            args.iter().cloned().filter(|arg| !matches!(arg.unpack(), GenericArgKind::Lifetime(_)));
+        if args.clone().next().is_some() {
+            self.generic_delimiters(|cx| cx.comma_sep(args))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'tcx> PrettyPrinter<'tcx> for CanonicalPrinter<'tcx> {
+    fn should_print_region(&self, region: ty::Region<'tcx>) -> bool {
+        region.get_name().is_some()
+    }
+
+    fn generic_delimiters(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<(), PrintError>,
+    ) -> Result<(), PrintError> {
+        self.write_str("<")?;
+        f(self)?;
+        self.write_str(">")
+    }
+}
+
+impl Write for CanonicalPrinter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.out.push_str(s);
+        Ok(())
+    }
+}
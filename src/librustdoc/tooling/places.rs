@@ -0,0 +1,87 @@
+//! Names for MIR places, after the user variables they are rooted at.
+
+use rustc_middle::mir::{Body, Place, ProjectionElem, VarDebugInfoContents};
+use rustc_middle::ty::{self, TyCtxt};
+use rustc_span::{Span, Symbol};
+
+/// A variable as described by the debuginfo of a body. Captured variables of closures are
+/// places rooted at the closure environment.
+pub(crate) struct UserVar<'tcx> {
+    pub(crate) name: Symbol,
+    pub(crate) place: Place<'tcx>,
+    /// Where the variable is declared.
+    pub(crate) span: Span,
+}
+
+/// The user variables of a body.
+pub(crate) struct UserVars<'tcx> {
+    vars: Vec<UserVar<'tcx>>,
+}
+
+impl<'tcx> UserVars<'tcx> {
+    pub(crate) fn new(body: &Body<'tcx>) -> Self {
+        let vars = body
+            .var_debug_info
+            .iter()
+            .filter(|info| info.composite.is_none())
+            .filter_map(|info| match info.value {
+                VarDebugInfoContents::Place(place) => {
+                    Some(UserVar { name: info.name, place, span: info.source_info.span })
+                }
+                VarDebugInfoContents::Const(_) => None,
+            })
+            .collect();
+        UserVars { vars }
+    }
+
+    /// Returns the variable `place` is rooted at, preferring the most precise one.
+    pub(crate) fn root_of(&self, place: Place<'tcx>) -> Option<&UserVar<'tcx>> {
+        self.vars
+            .iter()
+            .filter(|var| {
+                var.place.local == place.local
+                    && place.projection.starts_with(&var.place.projection[..])
+            })
+            .max_by_key(|var| var.place.projection.len())
+    }
+}
+
+/// Formats `place` as a path starting at the variable `var` it is rooted at, e.g. `(*x).field`.
+pub(crate) fn place_to_string<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &Body<'tcx>,
+    var: &UserVar<'tcx>,
+    place: Place<'tcx>,
+) -> String {
+    let mut path = var.name.to_string();
+    for (base, elem) in place.iter_projections().skip(var.place.projection.len()) {
+        if !matches!(elem, ProjectionElem::Deref) && path.starts_with('*') {
+            path = format!("({path})");
+        }
+        match elem {
+            ProjectionElem::Deref => path = format!("*{path}"),
+            ProjectionElem::Field(field, _) => {
+                let base_ty = base.ty(body, tcx);
+                match base_ty.ty.kind() {
+                    ty::Adt(def, _) => {
+                        let variant = match base_ty.variant_index {
+                            Some(index) => def.variant(index),
+                            None => def.non_enum_variant(),
+                        };
+                        path = format!("{path}.{}", variant.fields[field].name);
+                    }
+                    _ => path = format!("{path}.{}", field.index()),
+                }
+            }
+            ProjectionElem::Index(_)
+            | ProjectionElem::ConstantIndex { .. }
+            | ProjectionElem::Subslice { .. } => path = format!("{path}[..]"),
+            ProjectionElem::Downcast(name, index) => match name {
+                Some(name) => path = format!("({path} as {name})"),
+                None => path = format!("({path} as variant#{})", index.index()),
+            },
+            ProjectionElem::OpaqueCast(_) | ProjectionElem::Subtype(_) => {}
+        }
+    }
+    path
+}
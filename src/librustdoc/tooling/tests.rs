@@ -0,0 +1,45 @@
+use serde_json::json;
+
+use super::{is_from_macro, ExpansionInfo, MacroKindInfo, Record, SpanInfo};
+
+fn span(start_col: usize, end_col: usize, expansion: Option<ExpansionInfo>) -> SpanInfo {
+    SpanInfo {
+        start_line: 3,
+        start_col,
+        start_file: Some("src/lib.rs".into()),
+        end_line: 3,
+        end_col,
+        end_file: Some("src/lib.rs".into()),
+        from_expansion: expansion.is_some(),
+        expansion,
+    }
+}
+
+fn vec_expansion() -> ExpansionInfo {
+    ExpansionInfo {
+        macro_name: "vec".to_string(),
+        macro_kind: MacroKindInfo::Bang,
+        call_site: Box::new(span(4, 12, None)),
+    }
+}
+
+/// Serializes a use of `x` located at `span`.
+fn use_record(span: SpanInfo) -> serde_json::Value {
+    let mut record = json!({"record": "use", "name": "x", "ty": "u8", "binding": "0:1"});
+    let serde_json::Value::Object(span) = serde_json::to_value(span).unwrap() else {
+        unreachable!()
+    };
+    record.as_object_mut().unwrap().extend(span);
+    let record: Record = serde_json::from_value(record).unwrap();
+    serde_json::to_value(&record).unwrap()
+}
+
+#[test]
+fn records_expanded_from_macros_are_detected() {
+    assert!(is_from_macro(&use_record(span(4, 5, Some(vec_expansion())))));
+}
+
+#[test]
+fn records_written_at_the_call_site_are_not_from_macros() {
+    assert!(!is_from_macro(&use_record(span(4, 5, None))));
+}
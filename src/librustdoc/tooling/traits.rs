@@ -0,0 +1,59 @@
+//! Whether types implement auto traits and other traits commonly asked about in reviews.
+
+use rustc_hir::def_id::DefId;
+use rustc_infer::infer::TyCtxtInferExt;
+use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_middle::ty::{ParamEnv, Ty, TyCtxt};
+use rustc_span::sym;
+use rustc_trait_selection::infer::InferCtxtExt;
+
+/// Whether `ty` implements the trait `trait_def_id` in `param_env`, ignoring lifetimes.
+pub(crate) fn implements_trait<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    ty: Ty<'tcx>,
+    trait_def_id: DefId,
+) -> bool {
+    let infcx = tcx.infer_ctxt().build();
+    infcx
+        .type_implements_trait(trait_def_id, [tcx.erase_regions(ty)], param_env)
+        .must_apply_modulo_regions()
+}
+
+/// Returns the traits to check variable types against: `Send`, `Sync`, `Unpin`, `Copy`,
+/// `Clone`, `Debug` and `Default`, followed by the ones given with `--tooling-trait`, named as
+/// they were given.
+///
+/// Extra traits are looked up by their full path, e.g. `std::hash::Hash`. Traits that cannot be
+/// found, or that have generic parameters other than `Self`, are left out with a warning.
+pub(crate) fn traits_to_check(tcx: TyCtxt<'_>, extra_traits: &[String]) -> Vec<(String, DefId)> {
+    let lang_items = tcx.lang_items();
+    let mut traits: Vec<(String, DefId)> = [
+        ("Send", tcx.get_diagnostic_item(sym::Send)),
+        ("Sync", tcx.get_diagnostic_item(sym::Sync)),
+        ("Unpin", lang_items.unpin_trait()),
+        ("Copy", lang_items.copy_trait()),
+        ("Clone", lang_items.clone_trait()),
+        ("Debug", tcx.get_diagnostic_item(sym::Debug)),
+        ("Default", tcx.get_diagnostic_item(sym::Default)),
+    ]
+    .into_iter()
+    .filter_map(|(name, def_id)| Some((name.to_string(), def_id?)))
+    .collect();
+
+    for path in extra_traits {
+        let Some(def_id) = tcx
+            .all_traits()
+            .find(|&def_id| with_no_trimmed_paths!(tcx.def_path_str(def_id)) == *path)
+        else {
+            tcx.dcx().warn(format!("tooling: couldn't find trait `{path}`"));
+            continue;
+        };
+        if tcx.generics_of(def_id).count() != 1 {
+            tcx.dcx().warn(format!("tooling: trait `{path}` has generic parameters"));
+            continue;
+        }
+        traits.push((path.clone(), def_id));
+    }
+    traits
+}
@@ -0,0 +1,350 @@
+//! A structured representation of types, so that consumers don't have to parse type syntax.
+
+use rustc_hir::def_id::DefId;
+use rustc_hir::Unsafety;
+use rustc_middle::ty::{
+    self, ExistentialPredicate, GenericArgKind, GenericArgsRef, ParamEnv, Region, TermKind, Ty,
+    TyCtxt, TypeVisitableExt,
+};
+use serde::{Deserialize, Serialize};
+
+use super::closures::ClosureKind;
+use super::paths;
+
+/// An item a type refers to, such as an ADT or a function.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DefInfo {
+    /// The path of the item, e.g. `std::vec::Vec`.
+    path: String,
+    /// The crate the item is defined in.
+    crate_name: String,
+}
+
+impl DefInfo {
+    fn new(tcx: TyCtxt<'_>, def_id: DefId) -> Self {
+        DefInfo {
+            path: paths::def_path(tcx, def_id),
+            crate_name: tcx.crate_name(def_id.krate).to_string(),
+        }
+    }
+}
+
+/// A generic argument of a type, a trait or a function.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum GenericArgInfo {
+    /// A lifetime, with its name if it has one.
+    Lifetime(Option<String>),
+    Type(TyInfo),
+    /// A const, e.g. `3` or `N`.
+    Const(String),
+}
+
+impl GenericArgInfo {
+    fn from_args<'tcx>(tcx: TyCtxt<'tcx>, args: &[ty::GenericArg<'tcx>]) -> Vec<Self> {
+        args.iter()
+            .map(|arg| match arg.unpack() {
+                GenericArgKind::Lifetime(region) => GenericArgInfo::Lifetime(region_name(region)),
+                GenericArgKind::Type(ty) => GenericArgInfo::Type(TyInfo::new(tcx, ty)),
+                GenericArgKind::Const(ct) => GenericArgInfo::Const(paths::print(tcx, ct)),
+            })
+            .collect()
+    }
+}
+
+/// A predicate of a trait object type, e.g. `Iterator<Item = u32>` or `Send`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "predicate", rename_all = "snake_case")]
+pub enum PredicateInfo {
+    /// The principal trait, e.g. `Iterator`. The arguments leave out `Self`.
+    Trait {
+        #[serde(flatten)]
+        def: DefInfo,
+        args: Vec<GenericArgInfo>,
+    },
+    /// A constraint on an associated item of the principal trait, e.g. `Item = u32`.
+    Projection {
+        #[serde(flatten)]
+        def: DefInfo,
+        args: Vec<GenericArgInfo>,
+        term: GenericArgInfo,
+    },
+    /// An auto trait, e.g. `Send`.
+    AutoTrait {
+        #[serde(flatten)]
+        def: DefInfo,
+    },
+}
+
+/// The kind of an alias type.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AliasKind {
+    /// An associated type of a trait, e.g. `<I as Iterator>::Item`.
+    Projection,
+    /// An associated type of an inherent impl.
+    Inherent,
+    /// An `impl Trait` type.
+    Opaque,
+    /// A type alias that is not expanded eagerly.
+    Weak,
+}
+
+impl From<ty::AliasKind> for AliasKind {
+    fn from(kind: ty::AliasKind) -> Self {
+        match kind {
+            ty::Projection => AliasKind::Projection,
+            ty::Inherent => AliasKind::Inherent,
+            ty::Opaque => AliasKind::Opaque,
+            ty::Weak => AliasKind::Weak,
+        }
+    }
+}
+
+/// A type, tagged with its kind.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TyInfo {
+    /// `bool`, `char`, `str` and the integer and float types.
+    Primitive {
+        name: String,
+    },
+    Never,
+    /// A struct, enum or union.
+    Adt {
+        #[serde(flatten)]
+        def: DefInfo,
+        args: Vec<GenericArgInfo>,
+    },
+    /// A type declared in an `extern` block.
+    Foreign {
+        #[serde(flatten)]
+        def: DefInfo,
+    },
+    Ref {
+        /// The name of the lifetime, if it has one. Lifetimes are erased after type checking,
+        /// so only `'static` and lifetimes from signatures are named.
+        region: Option<String>,
+        mutable: bool,
+        pointee: Box<TyInfo>,
+    },
+    RawPtr {
+        mutable: bool,
+        pointee: Box<TyInfo>,
+    },
+    Tuple {
+        elements: Vec<TyInfo>,
+    },
+    Array {
+        element: Box<TyInfo>,
+        /// The length, if it is known without substituting generic parameters.
+        len: Option<u64>,
+        /// The length as written, e.g. `4` or `N`.
+        len_expr: String,
+    },
+    Slice {
+        element: Box<TyInfo>,
+    },
+    /// The zero-sized type of a function item.
+    FnDef {
+        #[serde(flatten)]
+        def: DefInfo,
+        args: Vec<GenericArgInfo>,
+    },
+    FnPtr {
+        inputs: Vec<TyInfo>,
+        output: Box<TyInfo>,
+        #[serde(rename = "unsafe")]
+        is_unsafe: bool,
+        abi: String,
+        c_variadic: bool,
+    },
+    /// A trait object, `dyn Trait` or `dyn* Trait`.
+    Dynamic {
+        predicates: Vec<PredicateInfo>,
+        region: Option<String>,
+        dyn_star: bool,
+    },
+    Closure {
+        #[serde(flatten)]
+        def: DefInfo,
+        /// The `Fn` trait the closure implements at most.
+        closure_kind: Option<ClosureKind>,
+        inputs: Vec<TyInfo>,
+        output: Box<TyInfo>,
+        /// The types of the captured places, in capture order.
+        upvars: Vec<TyInfo>,
+    },
+    /// An `async` closure.
+    CoroutineClosure {
+        #[serde(flatten)]
+        def: DefInfo,
+    },
+    /// The state machine of an `async` block or function, or of a coroutine.
+    Coroutine {
+        #[serde(flatten)]
+        def: DefInfo,
+    },
+    CoroutineWitness {
+        #[serde(flatten)]
+        def: DefInfo,
+    },
+    /// An associated type or an `impl Trait` type.
+    Alias {
+        alias_kind: AliasKind,
+        #[serde(flatten)]
+        def: DefInfo,
+        args: Vec<GenericArgInfo>,
+    },
+    /// A generic type parameter, e.g. `T`.
+    Param {
+        name: String,
+        index: u32,
+    },
+    /// A type that only appears while type checking, such as an inference variable.
+    Other {
+        repr: String,
+    },
+}
+
+impl TyInfo {
+    pub(crate) fn new<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Self {
+        let tys = |tys: &[Ty<'tcx>]| -> Vec<TyInfo> {
+            tys.iter().map(|&ty| TyInfo::new(tcx, ty)).collect()
+        };
+        let boxed = |ty: Ty<'tcx>| Box::new(TyInfo::new(tcx, ty));
+        match *ty.kind() {
+            ty::Bool | ty::Char | ty::Str | ty::Int(_) | ty::Uint(_) | ty::Float(_) => {
+                TyInfo::Primitive { name: ty.to_string() }
+            }
+            ty::Never => TyInfo::Never,
+            ty::Adt(adt_def, args) => TyInfo::Adt {
+                def: DefInfo::new(tcx, adt_def.did()),
+                args: GenericArgInfo::from_args(tcx, args),
+            },
+            ty::Foreign(def_id) => TyInfo::Foreign { def: DefInfo::new(tcx, def_id) },
+            ty::Ref(region, pointee, mutbl) => TyInfo::Ref {
+                region: region_name(region),
+                mutable: mutbl.is_mut(),
+                pointee: boxed(pointee),
+            },
+            ty::RawPtr(ty::TypeAndMut { ty: pointee, mutbl }) => {
+                TyInfo::RawPtr { mutable: mutbl.is_mut(), pointee: boxed(pointee) }
+            }
+            ty::Tuple(elements) => TyInfo::Tuple { elements: tys(elements) },
+            ty::Array(element, len) => TyInfo::Array {
+                element: boxed(element),
+                len: len.try_eval_target_usize(tcx, ty::ParamEnv::reveal_all()),
+                len_expr: paths::print(tcx, len),
+            },
+            ty::Slice(element) => TyInfo::Slice { element: boxed(element) },
+            ty::FnDef(def_id, args) => TyInfo::FnDef {
+                def: DefInfo::new(tcx, def_id),
+                args: GenericArgInfo::from_args(tcx, args),
+            },
+            ty::FnPtr(sig) => {
+                let sig = sig.skip_binder();
+                TyInfo::FnPtr {
+                    inputs: tys(sig.inputs()),
+                    output: boxed(sig.output()),
+                    is_unsafe: sig.unsafety == Unsafety::Unsafe,
+                    abi: sig.abi.name().to_string(),
+                    c_variadic: sig.c_variadic,
+                }
+            }
+            ty::Dynamic(predicates, region, dyn_kind) => TyInfo::Dynamic {
+                predicates: predicates
+                    .iter()
+                    .map(|predicate| PredicateInfo::new(tcx, predicate.skip_binder()))
+                    .collect(),
+                region: region_name(region),
+                dyn_star: dyn_kind == ty::DynStar,
+            },
+            ty::Closure(def_id, args) => {
+                let closure = args.as_closure();
+                let sig = closure.sig().skip_binder();
+                TyInfo::Closure {
+                    def: DefInfo::new(tcx, def_id),
+                    closure_kind: closure.kind_ty().to_opt_closure_kind().map(ClosureKind::from),
+                    inputs: tys(sig.inputs()),
+                    output: boxed(sig.output()),
+                    upvars: tys(closure.upvar_tys()),
+                }
+            }
+            ty::CoroutineClosure(def_id, _) => {
+                TyInfo::CoroutineClosure { def: DefInfo::new(tcx, def_id) }
+            }
+            ty::Coroutine(def_id, _) => TyInfo::Coroutine { def: DefInfo::new(tcx, def_id) },
+            ty::CoroutineWitness(def_id, _) => {
+                TyInfo::CoroutineWitness { def: DefInfo::new(tcx, def_id) }
+            }
+            ty::Alias(kind, alias_ty) => TyInfo::Alias {
+                alias_kind: kind.into(),
+                def: DefInfo::new(tcx, alias_ty.def_id),
+                args: GenericArgInfo::from_args(tcx, alias_ty.args),
+            },
+            ty::Param(param) => TyInfo::Param { name: param.name.to_string(), index: param.index },
+            ty::Bound(..) | ty::Placeholder(_) | ty::Infer(_) | ty::Error(_) => {
+                TyInfo::Other { repr: paths::print(tcx, ty) }
+            }
+        }
+    }
+}
+
+impl PredicateInfo {
+    fn new<'tcx>(tcx: TyCtxt<'tcx>, predicate: ExistentialPredicate<'tcx>) -> Self {
+        let args = |args: GenericArgsRef<'tcx>| GenericArgInfo::from_args(tcx, args);
+        match predicate {
+            ExistentialPredicate::Trait(trait_ref) => PredicateInfo::Trait {
+                def: DefInfo::new(tcx, trait_ref.def_id),
+                args: args(trait_ref.args),
+            },
+            ExistentialPredicate::Projection(projection) => PredicateInfo::Projection {
+                def: DefInfo::new(tcx, projection.def_id),
+                args: args(projection.args),
+                term: match projection.term.unpack() {
+                    TermKind::Ty(ty) => GenericArgInfo::Type(TyInfo::new(tcx, ty)),
+                    TermKind::Const(ct) => GenericArgInfo::Const(paths::print(tcx, ct)),
+                },
+            },
+            ExistentialPredicate::AutoTrait(def_id) => {
+                PredicateInfo::AutoTrait { def: DefInfo::new(tcx, def_id) }
+            }
+        }
+    }
+}
+
+/// The hidden type of a type that contains opaque types, such as `impl Iterator` or the future
+/// returned by an `async fn`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RevealedTyInfo {
+    ty: String,
+    ty_info: TyInfo,
+    /// The size in bytes, if the layout of the type can be computed.
+    size: Option<u64>,
+}
+
+impl RevealedTyInfo {
+    /// Returns `None` if `ty` contains no opaque types or if they cannot be revealed.
+    pub(crate) fn new<'tcx>(
+        tcx: TyCtxt<'tcx>,
+        param_env: ParamEnv<'tcx>,
+        ty: Ty<'tcx>,
+    ) -> Option<Self> {
+        if !ty.has_opaque_types() {
+            return None;
+        }
+        // Normalizing with `Reveal::All` replaces opaque types with their hidden types.
+        let param_env = param_env.with_reveal_all_normalized(tcx);
+        let revealed = tcx.try_normalize_erasing_regions(param_env, ty).ok()?;
+        Some(RevealedTyInfo {
+            ty: paths::print(tcx, revealed),
+            ty_info: TyInfo::new(tcx, revealed),
+            size: tcx.layout_of(param_env.and(revealed)).ok().map(|layout| layout.size.bytes()),
+        })
+    }
+}
+
+fn region_name(region: Region<'_>) -> Option<String> {
+    region.get_name().map(|name| name.to_string())
+}
@@ -0,0 +1,448 @@
+//! An inventory of `unsafe` code and of the unsafe operations it performs.
+//!
+//! The operations are found in the THIR of the bodies and classified like the THIR unsafety
+//! checker of `rustc_mir_build` does, whose classification is not public. Rustdoc never runs
+//! that checker, which is the query stealing the THIR, so the THIR is still available here.
+
+use std::ops::Bound;
+
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::{ItemKind, TraitFn, TraitItemKind, Unsafety};
+use rustc_middle::mir::BorrowKind;
+use rustc_middle::thir::visit::{self, Visitor};
+use rustc_middle::thir::{
+    AdtExpr, BindingMode, Block, BlockSafety, ClosureExpr, Expr, ExprKind, Pat, PatKind, Thir,
+};
+use rustc_middle::ty::{self, ParamEnv, TyCtxt};
+use rustc_span::Span;
+use serde::{Deserialize, Serialize};
+
+use super::{paths, Record, SpanInfo};
+
+/// The kind of `unsafe` code.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsafeKind {
+    Block,
+    Fn,
+    Impl,
+    Trait,
+}
+
+/// An operation that requires `unsafe`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsafeOpKind {
+    /// `*ptr` on a raw pointer.
+    RawPointerDeref,
+    /// A call to an `unsafe fn` or through an `unsafe fn` pointer.
+    UnsafeFnCall,
+    /// A call to a function declared in an `extern` block.
+    ExternCall,
+    /// A call to a function enabling `#[target_feature]`s the caller does not enable.
+    TargetFeatureCall,
+    /// A use of a `static mut`.
+    StaticMutAccess,
+    /// A use of a static declared in an `extern` block.
+    ExternStaticAccess,
+    /// A read of, or a borrow of, a union field, including through a pattern.
+    UnionFieldRead,
+    /// `asm!`
+    InlineAsm,
+    /// The construction of a type with a restricted range of valid values, such as
+    /// `NonNull`.
+    LayoutConstrainedInit,
+    /// A mutation or a mutable borrow of a field of a type with a restricted range of valid
+    /// values.
+    LayoutConstrainedFieldMutation,
+    /// A shared borrow of a field with interior mutability of a type with a restricted range
+    /// of valid values.
+    LayoutConstrainedFieldBorrow,
+}
+
+/// An unsafe operation.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UnsafeOpInfo {
+    #[serde(flatten)]
+    span: SpanInfo,
+    kind: UnsafeOpKind,
+    /// The called function or the accessed static, if any.
+    target: Option<String>,
+}
+
+/// A piece of `unsafe` code.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UnsafeInfo {
+    #[serde(flatten)]
+    span: SpanInfo,
+    kind: UnsafeKind,
+    /// The item, or for blocks, the item containing the block.
+    item: String,
+    /// The unsafe operations performed directly in an `unsafe` block, or in the body of an
+    /// `unsafe fn` outside of `unsafe` blocks.
+    operations: Vec<UnsafeOpInfo>,
+}
+
+fn add(inventory: &mut Vec<UnsafeInfo>, tcx: TyCtxt<'_>, kind: UnsafeKind, def_id: DefId) -> usize {
+    add_with_span(inventory, tcx, kind, def_id, tcx.def_span(def_id))
+}
+
+fn add_with_span(
+    inventory: &mut Vec<UnsafeInfo>,
+    tcx: TyCtxt<'_>,
+    kind: UnsafeKind,
+    def_id: DefId,
+    span: Span,
+) -> usize {
+    let unsafe_info = UnsafeInfo {
+        span: SpanInfo::new(tcx, span),
+        kind,
+        item: paths::def_path(tcx, def_id),
+        operations: Vec::new(),
+    };
+    inventory.push(unsafe_info);
+    inventory.len() - 1
+}
+
+fn is_layout_constrained(tcx: TyCtxt<'_>, def_id: DefId) -> bool {
+    tcx.layout_scalar_valid_range(def_id) != (Bound::Unbounded, Bound::Unbounded)
+}
+
+struct UnsafeVisitor<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    thir: &'a Thir<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    /// The `#[target_feature]`s enabled for the body.
+    body_target_features: &'tcx [rustc_span::Symbol],
+    inventory: &'a mut Vec<UnsafeInfo>,
+    /// Index into `inventory` of the innermost enclosing `unsafe` block or `unsafe fn`, or
+    /// `None` in safe code and in blocks the compiler made unsafe.
+    unsafe_context: Option<usize>,
+    /// Whether the visited expression is the place assigned to, which makes writing to a
+    /// union field safe.
+    in_assignee: bool,
+    /// Whether the visited pattern is matched against a union field.
+    in_union_destructure: bool,
+    /// Whether the visited pattern is matched against a type with a restricted range of valid
+    /// values.
+    inside_layout_constrained: bool,
+}
+
+impl<'a, 'tcx> UnsafeVisitor<'a, 'tcx> {
+    fn record_op(&mut self, kind: UnsafeOpKind, span: Span, target: Option<DefId>) {
+        let Some(index) = self.unsafe_context else { return };
+        let op_info = UnsafeOpInfo {
+            span: SpanInfo::new(self.tcx, span),
+            kind,
+            target: target.map(|def_id| paths::def_path(self.tcx, def_id)),
+        };
+        self.inventory[index].operations.push(op_info);
+    }
+
+    /// Whether `expr` is a place in a field of a type with a restricted range of valid values.
+    fn is_layout_constrained_place(&self, expr: &Expr<'tcx>) -> bool {
+        match expr.kind {
+            ExprKind::Field { lhs, .. } => {
+                let lhs = &self.thir[lhs];
+                let constrained = match lhs.ty.kind() {
+                    ty::Adt(adt_def, _) => is_layout_constrained(self.tcx, adt_def.did()),
+                    _ => false,
+                };
+                constrained || self.is_layout_constrained_place(lhs)
+            }
+            ExprKind::Scope { value: place, .. }
+            | ExprKind::Index { lhs: place, .. }
+            | ExprKind::PlaceTypeAscription { source: place, .. } => {
+                self.is_layout_constrained_place(&self.thir[place])
+            }
+            _ => false,
+        }
+    }
+
+    /// Visits the body of a closure or of an inline constant, which are part of the unsafe
+    /// context they are written in.
+    fn visit_inner_body(&mut self, def_id: LocalDefId) {
+        let Ok((thir, expr)) = self.tcx.thir_body(def_id) else { return };
+        let thir = thir.borrow();
+        let mut inner_visitor = UnsafeVisitor {
+            tcx: self.tcx,
+            thir: &thir,
+            param_env: self.param_env,
+            body_target_features: self.body_target_features,
+            inventory: self.inventory,
+            unsafe_context: self.unsafe_context,
+            in_assignee: false,
+            in_union_destructure: false,
+            inside_layout_constrained: false,
+        };
+        inner_visitor.visit_expr(&thir[expr]);
+    }
+}
+
+impl<'a, 'tcx> Visitor<'a, 'tcx> for UnsafeVisitor<'a, 'tcx> {
+    fn thir(&self) -> &'a Thir<'tcx> {
+        self.thir
+    }
+
+    fn visit_block(&mut self, block: &'a Block) {
+        let unsafe_context = match block.safety_mode {
+            BlockSafety::Safe => return visit::walk_block(self, block),
+            // Blocks the compiler made unsafe, e.g. in the expansion of `pin!`, are not part of
+            // the unsafe surface of the crate.
+            BlockSafety::BuiltinUnsafe => None,
+            BlockSafety::ExplicitUnsafe(hir_id) => Some(add_with_span(
+                self.inventory,
+                self.tcx,
+                UnsafeKind::Block,
+                hir_id.owner.to_def_id(),
+                block.span,
+            )),
+        };
+        let outer_context = std::mem::replace(&mut self.unsafe_context, unsafe_context);
+        visit::walk_block(self, block);
+        self.unsafe_context = outer_context;
+    }
+
+    fn visit_pat(&mut self, pat: &'a Pat<'tcx>) {
+        // Matching a union field against anything but a wildcard reads it.
+        if self.in_union_destructure
+            && matches!(
+                pat.kind,
+                PatKind::Binding { .. }
+                    | PatKind::Constant { .. }
+                    | PatKind::Variant { .. }
+                    | PatKind::Leaf { .. }
+                    | PatKind::Deref { .. }
+                    | PatKind::Range { .. }
+                    | PatKind::Slice { .. }
+                    | PatKind::Array { .. }
+            )
+        {
+            return self.record_op(UnsafeOpKind::UnionFieldRead, pat.span, None);
+        }
+
+        match &pat.kind {
+            PatKind::Leaf { .. } if let ty::Adt(adt_def, _) = pat.ty.kind() => {
+                let (in_union_destructure, inside_layout_constrained) = if adt_def.is_union() {
+                    (true, self.inside_layout_constrained)
+                } else {
+                    (false, is_layout_constrained(self.tcx, adt_def.did()))
+                };
+                let outer = (self.in_union_destructure, self.inside_layout_constrained);
+                self.in_union_destructure = in_union_destructure;
+                self.inside_layout_constrained |= inside_layout_constrained;
+                visit::walk_pat(self, pat);
+                (self.in_union_destructure, self.inside_layout_constrained) = outer;
+            }
+            PatKind::Binding { mode: BindingMode::ByRef(borrow_kind), ty, .. }
+                if self.inside_layout_constrained =>
+            {
+                match borrow_kind {
+                    BorrowKind::Mut { .. } => {
+                        self.record_op(UnsafeOpKind::LayoutConstrainedFieldMutation, pat.span, None)
+                    }
+                    BorrowKind::Fake | BorrowKind::Shared => {
+                        if let ty::Ref(_, ty, _) = ty.kind()
+                            && !ty.is_freeze(self.tcx, self.param_env)
+                        {
+                            self.record_op(
+                                UnsafeOpKind::LayoutConstrainedFieldBorrow,
+                                pat.span,
+                                None,
+                            );
+                        }
+                    }
+                }
+                visit::walk_pat(self, pat);
+            }
+            PatKind::Deref { .. } => {
+                let outer = std::mem::replace(&mut self.inside_layout_constrained, false);
+                visit::walk_pat(self, pat);
+                self.inside_layout_constrained = outer;
+            }
+            PatKind::InlineConstant { def, .. } => {
+                self.visit_inner_body(*def);
+                visit::walk_pat(self, pat);
+            }
+            _ => visit::walk_pat(self, pat),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr<'tcx>) {
+        // Only places stay the assignee; a dereference or any value leaves it.
+        if !matches!(
+            expr.kind,
+            ExprKind::Field { .. }
+                | ExprKind::VarRef { .. }
+                | ExprKind::UpvarRef { .. }
+                | ExprKind::Scope { .. }
+                | ExprKind::Cast { .. }
+        ) {
+            self.in_assignee = false;
+        }
+
+        match expr.kind {
+            ExprKind::Call { fun, .. } => {
+                let fun_ty = self.thir[fun].ty;
+                let callee = match *fun_ty.kind() {
+                    ty::FnDef(def_id, _) => Some(def_id),
+                    _ => None,
+                };
+                if fun_ty.fn_sig(self.tcx).unsafety() == Unsafety::Unsafe {
+                    let kind = if callee.is_some_and(|def_id| self.tcx.is_foreign_item(def_id)) {
+                        UnsafeOpKind::ExternCall
+                    } else {
+                        UnsafeOpKind::UnsafeFnCall
+                    };
+                    self.record_op(kind, expr.span, callee);
+                } else if let Some(callee) = callee
+                    && !self.tcx.sess.target.options.is_like_wasm
+                    && !self
+                        .tcx
+                        .codegen_fn_attrs(callee)
+                        .target_features
+                        .iter()
+                        .all(|feature| self.body_target_features.contains(feature))
+                {
+                    self.record_op(UnsafeOpKind::TargetFeatureCall, expr.span, Some(callee));
+                }
+            }
+            ExprKind::Deref { arg } => match self.thir[arg].kind {
+                ExprKind::StaticRef { def_id, .. } | ExprKind::ThreadLocalRef(def_id) => {
+                    if self.tcx.is_mutable_static(def_id) {
+                        self.record_op(UnsafeOpKind::StaticMutAccess, expr.span, Some(def_id));
+                    } else if self.tcx.is_foreign_item(def_id) {
+                        self.record_op(UnsafeOpKind::ExternStaticAccess, expr.span, Some(def_id));
+                    }
+                }
+                _ if self.thir[arg].ty.is_unsafe_ptr() => {
+                    self.record_op(UnsafeOpKind::RawPointerDeref, expr.span, None);
+                }
+                _ => {}
+            },
+            ExprKind::InlineAsm(_) => self.record_op(UnsafeOpKind::InlineAsm, expr.span, None),
+            ExprKind::Adt(box AdtExpr { adt_def, .. })
+                if is_layout_constrained(self.tcx, adt_def.did()) =>
+            {
+                self.record_op(UnsafeOpKind::LayoutConstrainedInit, expr.span, None);
+            }
+            ExprKind::Closure(box ClosureExpr { closure_id, .. }) => {
+                self.visit_inner_body(closure_id);
+            }
+            ExprKind::ConstBlock { did, .. } => self.visit_inner_body(did.expect_local()),
+            ExprKind::Field { lhs, .. } => {
+                if let ty::Adt(adt_def, _) = self.thir[lhs].ty.kind()
+                    && adt_def.is_union()
+                    && !self.in_assignee
+                {
+                    self.record_op(UnsafeOpKind::UnionFieldRead, expr.span, None);
+                }
+            }
+            ExprKind::Assign { lhs, rhs } | ExprKind::AssignOp { lhs, rhs, .. } => {
+                let lhs = &self.thir[lhs];
+                if self.is_layout_constrained_place(lhs) {
+                    self.record_op(UnsafeOpKind::LayoutConstrainedFieldMutation, expr.span, None);
+                }
+                // Writing to a union field is safe, but `+=` and the like also read it.
+                if let ExprKind::Assign { .. } = expr.kind {
+                    self.in_assignee = true;
+                    visit::walk_expr(self, lhs);
+                    self.in_assignee = false;
+                    return self.visit_expr(&self.thir[rhs]);
+                }
+            }
+            ExprKind::Borrow { borrow_kind, arg } => {
+                let arg = &self.thir[arg];
+                if self.is_layout_constrained_place(arg) {
+                    match borrow_kind {
+                        BorrowKind::Mut { .. } => self.record_op(
+                            UnsafeOpKind::LayoutConstrainedFieldMutation,
+                            expr.span,
+                            None,
+                        ),
+                        BorrowKind::Fake | BorrowKind::Shared
+                            if !arg.ty.is_freeze(self.tcx, self.param_env) =>
+                        {
+                            self.record_op(
+                                UnsafeOpKind::LayoutConstrainedFieldBorrow,
+                                expr.span,
+                                None,
+                            )
+                        }
+                        BorrowKind::Fake | BorrowKind::Shared => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+/// Records the `unsafe fn` owning `def_id`, if any, and the unsafe blocks and operations of
+/// its body.
+fn collect_body(tcx: TyCtxt<'_>, def_id: LocalDefId, inventory: &mut Vec<UnsafeInfo>) {
+    let hir_id = tcx.local_def_id_to_hir_id(def_id);
+    let unsafe_fn = tcx
+        .hir()
+        .fn_sig_by_hir_id(hir_id)
+        .is_some_and(|fn_sig| fn_sig.header.unsafety == Unsafety::Unsafe);
+    let unsafe_context = unsafe_fn.then(|| add(inventory, tcx, UnsafeKind::Fn, def_id.to_def_id()));
+
+    let Ok((thir, expr)) = tcx.thir_body(def_id) else { return };
+    let thir = thir.borrow();
+    // The THIR is empty if the body has type errors.
+    if thir.exprs.is_empty() {
+        return;
+    }
+    let mut visitor = UnsafeVisitor {
+        tcx,
+        thir: &thir,
+        param_env: tcx.param_env(def_id),
+        body_target_features: &tcx.body_codegen_attrs(def_id.to_def_id()).target_features,
+        inventory,
+        unsafe_context,
+        in_assignee: false,
+        in_union_destructure: false,
+        inside_layout_constrained: false,
+    };
+    visitor.visit_expr(&thir[expr]);
+}
+
+pub(crate) fn collect(tcx: TyCtxt<'_>) -> Vec<Record> {
+    if !tcx.sess.opts.unstable_opts.thir_unsafeck {
+        // MIR building steals the THIR when the THIR unsafety checker is disabled.
+        tcx.dcx().warn("the `unsafe` tooling analysis needs `-Zthir-unsafeck`, skipping it");
+        return Vec::new();
+    }
+
+    let mut inventory = Vec::new();
+    let hir = tcx.hir();
+    for item_id in hir.items() {
+        let def_id = item_id.owner_id.to_def_id();
+        match hir.item(item_id).kind {
+            ItemKind::Impl(impl_) if impl_.unsafety == Unsafety::Unsafe => {
+                add(&mut inventory, tcx, UnsafeKind::Impl, def_id);
+            }
+            ItemKind::Trait(_, Unsafety::Unsafe, ..) => {
+                add(&mut inventory, tcx, UnsafeKind::Trait, def_id);
+            }
+            _ => {}
+        }
+    }
+    // Trait methods without a default body are not body owners.
+    for trait_item_id in tcx.hir_crate_items(()).trait_items() {
+        if let TraitItemKind::Fn(sig, TraitFn::Required(_)) = hir.trait_item(trait_item_id).kind
+            && sig.header.unsafety == Unsafety::Unsafe
+        {
+            add(&mut inventory, tcx, UnsafeKind::Fn, trait_item_id.owner_id.to_def_id());
+        }
+    }
+    for def_id in hir.body_owners() {
+        // Closures and inline constants are visited with the body they are written in.
+        if !tcx.is_typeck_child(def_id.to_def_id()) {
+            collect_body(tcx, def_id, &mut inventory);
+        }
+    }
+    inventory.into_iter().map(Record::Unsafe).collect()
+}
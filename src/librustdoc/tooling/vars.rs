@@ -0,0 +1,382 @@
+//! Variable bindings, their uses and assignments to them, and the bounds in scope.
+
+use std::collections::BTreeMap;
+
+use rustc_hir::def::Res;
+use rustc_hir::def_id::DefId;
+use rustc_hir::{
+    BinOpKind, BindingAnnotation, BodyId, Expr, ExprKind, HirId, LocalSource, LoopSource,
+    MatchSource, Mutability, Node, Pat, QPath, UnOp,
+};
+use rustc_middle::ty::{self, TyCtxt, TypeVisitableExt, TypeckResults};
+use rustc_span::symbol::Ident;
+use rustc_span::Span;
+use serde::{Deserialize, Serialize};
+
+use super::generics::GenericsInfo;
+use super::layouts::LayoutInfo;
+use super::traits::{implements_trait, traits_to_check};
+use super::types::{RevealedTyInfo, TyInfo};
+use super::{binding_id, paths, Record, SpanInfo, ToolingOptions};
+
+/// How a binding holds its value, after default binding modes have been applied.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BindingMode {
+    ByValue,
+    Ref,
+    RefMut,
+}
+
+/// The construct that introduced a binding.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BindingOrigin {
+    /// `let <pat> = ..;`
+    Let,
+    /// `let <pat> = .. else { .. };`
+    LetElse,
+    /// A parameter of a function or method.
+    FnParam,
+    /// A parameter of a closure.
+    ClosureParam,
+    /// `match .. { <pat> => .. }`
+    MatchArm,
+    /// `if let <pat> = ..`, including the `let`s of a let chain.
+    IfLet,
+    /// `while let <pat> = ..`
+    WhileLet,
+    /// `<pat> if let <pat> = .. =>` in a match arm guard.
+    LetGuard,
+    /// `for <pat> in ..`
+    ForLoop,
+}
+
+#[allow(unused_variables)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VarInfo {
+    /// Identifies this binding across the `use` and `assign` records referring to it.
+    id: String,
+    name: String,
+    #[serde(flatten)]
+    span: SpanInfo,
+    /// The item whose body declares the binding. Its `generics` record lists the bounds in
+    /// scope.
+    owner: String,
+    ty: Option<String>,
+    /// `ty` with its associated types normalized, if that changes it.
+    normalized_ty: Option<String>,
+    /// The structure of `ty`, for consumers that should not have to parse it.
+    ty_info: Option<TyInfo>,
+    /// The hidden type behind the opaque types in `ty`, with `--tooling-reveal-opaque-types`.
+    revealed_ty: Option<RevealedTyInfo>,
+    /// The layout of `ty`, if it does not depend on generic parameters.
+    layout: Option<LayoutInfo>,
+    /// Whether `ty` implements `Send`, `Sync`, `Unpin`, `Copy`, `Clone`, `Debug`, `Default`
+    /// and the traits given with `--tooling-trait`, in the scope of the binding.
+    traits: BTreeMap<String, bool>,
+    binding_mode: BindingMode,
+    /// Whether the binding was declared `mut`.
+    mutable: bool,
+    introduced_by: BindingOrigin,
+}
+
+/// An assignment to a place (e.g., `a = b`, `self.len += 1` or `*p = v`).
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AssignInfo {
+    /// The assignee, e.g. `self.buf[i]`.
+    place: String,
+    #[serde(flatten)]
+    span: SpanInfo,
+    /// `=` for plain assignments, or the compound operator such as `+=`.
+    op: String,
+    place_ty: Option<String>,
+    value_ty: Option<String>,
+    /// The binding the assigned place is rooted in, if it is a local variable.
+    binding: Option<String>,
+}
+
+/// A use of a local variable (e.g., the `x` in `foo(x)`).
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UseInfo {
+    name: String,
+    #[serde(flatten)]
+    span: SpanInfo,
+    ty: Option<String>,
+    /// The `id` of the used binding.
+    binding: String,
+}
+
+struct HirVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    /// Typeck results of the body currently being visited.
+    maybe_typeck_results: Option<&'tcx TypeckResults<'tcx>>,
+    /// Whether to reveal the hidden types of opaque types in the types of bindings.
+    reveal_opaque_types: bool,
+    /// The traits variable types are checked against, with the names they are reported under.
+    traits: Vec<(String, DefId)>,
+    records: Vec<Record>,
+}
+
+impl<'tcx> HirVisitor<'tcx> {
+    /// Records every binding introduced by `pat`, including the ones nested in tuple, struct,
+    /// slice and reference patterns.
+    ///
+    /// Bindings made up by the compiler while desugaring `for` loops, `?`, `.await` and async
+    /// functions are skipped; the ones the user wrote inside those constructs are kept.
+    fn record_bindings(&mut self, pat: &'tcx Pat<'tcx>, origin: BindingOrigin) {
+        pat.each_binding(|annotation, hir_id, span, ident| {
+            if span.desugaring_kind().is_none() {
+                self.record_binding(annotation, hir_id, span, ident, origin);
+            }
+        });
+    }
+
+    fn record_binding(
+        &mut self,
+        annotation: BindingAnnotation,
+        hir_id: HirId,
+        span: Span,
+        ident: Ident,
+        origin: BindingOrigin,
+    ) {
+        let typeck_results = self.maybe_typeck_results;
+        // The type of a binding pattern is the type of the bound variable, so for `ref x` this
+        // is a reference, unlike the type of the matched place.
+        let ty = typeck_results.and_then(|results| results.node_type_opt(hir_id));
+        let mode = typeck_results
+            .and_then(|results| results.pat_binding_modes().get(hir_id).copied())
+            .unwrap_or_else(|| ty::BindingMode::convert(annotation));
+        let (binding_mode, mutable) = match mode {
+            ty::BindByValue(mutbl) => (BindingMode::ByValue, mutbl.is_mut()),
+            ty::BindByReference(Mutability::Not) => (BindingMode::Ref, false),
+            ty::BindByReference(Mutability::Mut) => (BindingMode::RefMut, false),
+        };
+
+        let var_info = VarInfo {
+            id: binding_id(self.tcx, hir_id),
+            name: ident.name.as_str().to_string(),
+            span: SpanInfo::new(self.tcx, span),
+            owner: paths::def_path(self.tcx, hir_id.owner.to_def_id()),
+            ty: ty.map(|ty| paths::print(self.tcx, ty)),
+            normalized_ty: ty.filter(|ty| ty.has_projections()).and_then(|ty| {
+                let param_env = self.tcx.param_env(hir_id.owner);
+                let ty = self.tcx.erase_regions(ty);
+                let normalized = self.tcx.try_normalize_erasing_regions(param_env, ty).ok()?;
+                (normalized != ty).then(|| paths::print(self.tcx, normalized))
+            }),
+            ty_info: ty.map(|ty| TyInfo::new(self.tcx, ty)),
+            revealed_ty: ty
+                .filter(|_| self.reveal_opaque_types)
+                .and_then(|ty| RevealedTyInfo::new(self.tcx, self.tcx.param_env(hir_id.owner), ty)),
+            layout: ty.and_then(|ty| {
+                let param_env = self.tcx.param_env_reveal_all_normalized(hir_id.owner);
+                LayoutInfo::new(self.tcx, param_env, ty)
+            }),
+            traits: ty
+                .filter(|ty| !ty.references_error())
+                .map(|ty| {
+                    let param_env = self.tcx.param_env(hir_id.owner);
+                    self.traits
+                        .iter()
+                        .map(|(name, def_id)| {
+                            (name.clone(), implements_trait(self.tcx, param_env, ty, *def_id))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            binding_mode,
+            mutable,
+            introduced_by: origin,
+        };
+        self.push(Record::Var(var_info));
+    }
+
+    fn record_assignment(&mut self, place: &'tcx Expr<'tcx>, value: &'tcx Expr<'tcx>, op: &str) {
+        let typeck_results = self.maybe_typeck_results;
+        let assign_info = AssignInfo {
+            place: rustc_hir_pretty::id_to_string(&self.tcx.hir(), place.hir_id),
+            span: SpanInfo::new(self.tcx, place.span),
+            op: op.to_string(),
+            place_ty: typeck_results
+                .and_then(|results| results.expr_ty_opt(place))
+                .map(|ty| paths::print(self.tcx, ty)),
+            value_ty: typeck_results
+                .and_then(|results| results.expr_ty_opt(value))
+                .map(|ty| paths::print(self.tcx, ty)),
+            binding: place_root_binding(place).map(|hir_id| binding_id(self.tcx, hir_id)),
+        };
+        self.push(Record::Assign(assign_info));
+    }
+
+    fn record_use(&mut self, ex: &'tcx Expr<'tcx>, path: &rustc_hir::Path<'tcx>, hir_id: HirId) {
+        let use_info = UseInfo {
+            name: path.segments[0].ident.to_string(),
+            span: SpanInfo::new(self.tcx, ex.span),
+            ty: self
+                .maybe_typeck_results
+                .and_then(|results| results.expr_ty_opt(ex))
+                .map(|ty| paths::print(self.tcx, ty)),
+            binding: binding_id(self.tcx, hir_id),
+        };
+        self.push(Record::Use(use_info));
+    }
+
+    fn push(&mut self, record: Record) {
+        self.records.push(record);
+    }
+
+    /// Finds out which construct the `let` expression `hir_id` belongs to.
+    fn let_expr_origin(&self, hir_id: HirId) -> BindingOrigin {
+        let hir = self.tcx.hir();
+        for (parent_id, node) in hir.parent_iter(hir_id) {
+            match node {
+                // Let chains and the conditions of `if`s are made up of these.
+                Node::Expr(Expr { kind: ExprKind::Binary(op, ..), .. })
+                    if op.node == BinOpKind::And => {}
+                Node::Expr(Expr { kind: ExprKind::DropTemps(_), .. }) => {}
+                Node::Expr(Expr { kind: ExprKind::If(..), .. }) => {
+                    // `while let` is lowered to `loop { if let .. { .. } else { break } }`.
+                    return match hir.find_parent(hir.parent_id(parent_id)) {
+                        Some(Node::Expr(Expr {
+                            kind: ExprKind::Loop(_, _, LoopSource::While, _),
+                            ..
+                        })) => BindingOrigin::WhileLet,
+                        _ => BindingOrigin::IfLet,
+                    };
+                }
+                Node::Arm(_) => return BindingOrigin::LetGuard,
+                _ => break,
+            }
+        }
+        BindingOrigin::IfLet
+    }
+}
+
+/// Returns the local variable the place expression `expr` is projected from, looking through
+/// field accesses, indexing and dereferences.
+fn place_root_binding(mut expr: &Expr<'_>) -> Option<HirId> {
+    loop {
+        match expr.kind {
+            ExprKind::Field(base, _)
+            | ExprKind::Index(base, _, _)
+            | ExprKind::Unary(UnOp::Deref, base) => expr = base,
+            ExprKind::Path(QPath::Resolved(None, path)) => {
+                return if let Res::Local(hir_id) = path.res { Some(hir_id) } else { None };
+            }
+            _ => return None,
+        }
+    }
+}
+
+impl<'tcx> rustc_hir::intravisit::Visitor<'tcx> for HirVisitor<'tcx> {
+    type Map = rustc_middle::hir::map::Map<'tcx>;
+    type NestedFilter = rustc_middle::hir::nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_nested_body(&mut self, body_id: BodyId) {
+        let old_typeck_results = self.maybe_typeck_results.replace(self.tcx.typeck_body(body_id));
+        let body = self.tcx.hir().body(body_id);
+        // Closures and inline consts share the generic context of the item they are in.
+        let owner = self.tcx.hir().body_owner_def_id(body_id);
+        if body.value.hir_id.owner.def_id == owner {
+            let generics_info = GenericsInfo::new(self.tcx, owner.to_def_id());
+            self.push(Record::Generics(generics_info));
+        }
+        self.visit_body(body);
+        self.maybe_typeck_results = old_typeck_results;
+    }
+
+    // Represents a `let` statement (i.e., `let <pat>:<ty> = <init>;`).
+    // The types come from the patterns themselves, so `let x;` without an initializer
+    // still gets the type that inference settled on.
+    fn visit_local(&mut self, local: &'tcx rustc_hir::Local<'tcx>) {
+        let origin = match local.source {
+            // The temporaries of destructuring assignments are not user variables; the
+            // assignments they are used in are recorded instead.
+            LocalSource::AssignDesugar(_) => return rustc_hir::intravisit::walk_local(self, local),
+            // The parameters of async functions are rebound inside of the returned future.
+            LocalSource::AsyncFn => BindingOrigin::FnParam,
+            _ if local.els.is_some() => BindingOrigin::LetElse,
+            _ => BindingOrigin::Let,
+        };
+        self.record_bindings(local.pat, origin);
+        rustc_hir::intravisit::walk_local(self, local);
+    }
+
+    // A parameter of a function, method or closure body (e.g., `|(a, b): (u8, u8)| ..`).
+    fn visit_param(&mut self, param: &'tcx rustc_hir::Param<'tcx>) {
+        let origin = match self.tcx.hir().get_parent(param.hir_id) {
+            Node::Expr(Expr { kind: ExprKind::Closure(..), .. }) => BindingOrigin::ClosureParam,
+            _ => BindingOrigin::FnParam,
+        };
+        self.record_bindings(param.pat, origin);
+        rustc_hir::intravisit::walk_param(self, param);
+    }
+
+    // A `match` arm, also used by the desugaring of `for` loops.
+    fn visit_arm(&mut self, arm: &'tcx rustc_hir::Arm<'tcx>) {
+        if let Node::Expr(Expr { kind: ExprKind::Match(_, _, source), .. }) =
+            self.tcx.hir().get_parent(arm.hir_id)
+        {
+            match source {
+                MatchSource::Normal => self.record_bindings(arm.pat, BindingOrigin::MatchArm),
+                MatchSource::ForLoopDesugar => {
+                    self.record_bindings(arm.pat, BindingOrigin::ForLoop)
+                }
+                MatchSource::TryDesugar(_)
+                | MatchSource::AwaitDesugar
+                | MatchSource::FormatArgs => {}
+            }
+        }
+        rustc_hir::intravisit::walk_arm(self, arm);
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx Expr<'tcx>) {
+        match ex.kind {
+            // LetExpr: `let <pat> = <expr>` in the condition of an `if`, `while` or match guard.
+            ExprKind::Let(let_expr) => {
+                let origin = self.let_expr_origin(ex.hir_id);
+                self.record_bindings(let_expr.pat, origin);
+            }
+            // PathExpr: A use of a local variable (e.g., `x`).
+            ExprKind::Path(QPath::Resolved(None, path))
+                if let Res::Local(hir_id) = path.res
+                    && ex.span.desugaring_kind().is_none() =>
+            {
+                self.record_use(ex, path, hir_id)
+            }
+            // AssignExpr: An assignment (e.g., `a = foo()` or `self.buf[i] = b`).
+            ExprKind::Assign(place, value, _) => {
+                self.record_assignment(place, value, "=");
+                // `x = ..` overwrites `x` without using it, so don't record a use for `x`.
+                if let ExprKind::Path(QPath::Resolved(None, path)) = place.kind
+                    && let Res::Local(_) = path.res
+                {
+                    return self.visit_expr(value);
+                }
+            }
+            // AssignOpExpr: A compound assignment (e.g., `a += 1`).
+            ExprKind::AssignOp(op, place, value) => {
+                self.record_assignment(place, value, &format!("{}=", op.node.as_str()))
+            }
+            _ => {}
+        }
+        rustc_hir::intravisit::walk_expr(self, ex);
+    }
+}
+
+pub(crate) fn collect(tcx: TyCtxt<'_>, options: &ToolingOptions) -> Vec<Record> {
+    let mut visitor = HirVisitor {
+        tcx,
+        maybe_typeck_results: None,
+        reveal_opaque_types: options.reveal_opaque_types,
+        traits: traits_to_check(tcx, &options.extra_traits),
+        records: Vec::new(),
+    };
+    tcx.hir().visit_all_item_likes_in_crate(&mut visitor);
+    visitor.records
+}